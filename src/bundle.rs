@@ -0,0 +1,482 @@
+use crate::config::{get_verified_config, ConfigError, TourConfig, Verification};
+use crate::fetch::{resolve_sessions, DemoFetchError};
+use crate::tasks::{get_filename, git_mirror, save_file};
+use chrono::Utc;
+use custom_error::custom_error;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+
+pub const BUNDLE_VERSION: u32 = 1;
+const CONFIG_FILE: &str = "demo.yml";
+const MANIFEST_FILE: &str = "manifest.json";
+const FILES_DIRECTORY: &str = "files";
+
+custom_error! {
+    pub DemoBundleError
+    ConfigFailure{source: ConfigError} = "{source}",
+    FetchFailure{source: DemoFetchError} = "{source}",
+    NotAdded{source_path: String, message: String} = "Failed to add {source_path} to the bundle: {message}",
+    WriteFailure{path: String, source: io::Error} = "Failed to write the bundle to {path}: {source}",
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BundleCommand {
+    pub config_path: String,
+    pub verification: Verification,
+    pub profile: Option<String>,
+    pub session_names: Option<Vec<String>>,
+    pub event: Option<String>,
+    pub output_path: PathBuf,
+}
+
+// Lists every file of a bundle with its checksum, and where the slides, videos, repositories and
+// templates came from.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub created: String,
+    pub event: Option<String>,
+    pub sessions: Vec<String>,
+    pub sources: BTreeMap<String, String>,
+    pub files: Vec<ManifestFile>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestFile {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+impl BundleCommand {
+    pub fn new(config_path: String, output_path: String) -> Self {
+        BundleCommand {
+            config_path,
+            verification: Verification::default(),
+            profile: None,
+            session_names: None,
+            event: None,
+            output_path: PathBuf::from(output_path),
+        }
+    }
+
+    // Everything is gathered in a directory next to the bundle first, so a failed download does
+    // not leave half a bundle behind, and the files are not copied between disks.
+    pub fn execute(&self) -> Result<(), DemoBundleError> {
+        let config = get_verified_config(
+            &self.config_path,
+            self.profile.as_deref(),
+            &self.verification,
+        )?;
+        let session_names = match self.session_names {
+            Some(ref s) => s.clone(),
+            None => config.sessions().iter().map(|s| s.name()).collect(),
+        };
+        let config = resolve_sessions(config, &session_names, self.event.clone())?;
+
+        let directory = staging_directory(&self.output_path);
+        let _ = fs::remove_dir_all(&directory);
+        let result = self.stage(config, &directory).and_then(|manifest| {
+            write_archive(&directory, &self.output_path).map_err(|source| {
+                DemoBundleError::WriteFailure {
+                    path: self.output_path.to_string_lossy().to_string(),
+                    source,
+                }
+            })?;
+            Ok(manifest)
+        });
+        let _ = fs::remove_dir_all(&directory);
+
+        let manifest = result?;
+        println!(
+            "\nWrote {} with {} sessions and {} files.",
+            self.output_path.display(),
+            manifest.sessions.len(),
+            manifest.files.len()
+        );
+        Ok(())
+    }
+
+    fn stage(&self, mut config: TourConfig, directory: &Path) -> Result<Manifest, DemoBundleError> {
+        fs::create_dir_all(directory).map_err(|source| DemoBundleError::WriteFailure {
+            path: directory.to_string_lossy().to_string(),
+            source,
+        })?;
+        let mut bundle = Bundle::new(directory);
+        let mut session_names = Vec::new();
+        for session in config.sessions.iter_mut().flatten() {
+            let name = session.name();
+            println!("\t{}: Adding to the bundle", &name);
+            let session_directory = format!("{}/{}", FILES_DIRECTORY, path_component(&name));
+
+            if let Some(ref mut download) = session.slides {
+                if let Some(ref url) = download.url {
+                    let file_name = get_filename(url, download.file_name.as_deref().unwrap_or(""));
+                    let slides_directory = format!("{}/slides", &session_directory);
+                    download.url = Some(bundle.add_file(url, &slides_directory, &file_name)?);
+                }
+            }
+            for download in session.videos.iter_mut().flatten() {
+                if let Some(ref url) = download.url {
+                    let file_name = get_filename(url, "");
+                    let videos_directory = format!("{}/videos", &session_directory);
+                    download.url = Some(bundle.add_file(url, &videos_directory, &file_name)?);
+                }
+            }
+            for repo in session.git_repos.iter_mut().flatten() {
+                let url = repo.url();
+                let repos_directory = format!("{}/repos", &session_directory);
+                repo.url = Some(bundle.add_repo(&url, &repos_directory)?);
+            }
+
+            // Templates are often shared between sessions, so they are kept together.
+            let templates_directory = format!("{}/templates", FILES_DIRECTORY);
+            for command in session.commands.iter_mut().flatten() {
+                let template = match command.template {
+                    Some(ref mut t) => t,
+                    None => continue,
+                };
+                if let Some(source) = template.path.take().or_else(|| template.url.clone()) {
+                    template.url = None;
+                    let file_name = get_filename(&source, "");
+                    template.path =
+                        Some(bundle.add_file(&source, &templates_directory, &file_name)?);
+                }
+                if let Some(source) = template.parameters_file.take() {
+                    let file_name = get_filename(&source, "");
+                    template.parameters_file =
+                        Some(bundle.add_file(&source, &templates_directory, &file_name)?);
+                }
+            }
+            session_names.push(name);
+        }
+
+        config.write(&directory.join(CONFIG_FILE));
+        let files = checksums(directory).map_err(|e| DemoBundleError::NotAdded {
+            source_path: directory.to_string_lossy().to_string(),
+            message: e.to_string(),
+        })?;
+        let manifest = Manifest {
+            version: BUNDLE_VERSION,
+            created: Utc::now().to_rfc3339(),
+            event: self.event.clone(),
+            sessions: session_names,
+            sources: bundle.sources,
+            files,
+        };
+        let content = serde_json::to_string_pretty(&manifest).unwrap();
+        fs::write(directory.join(MANIFEST_FILE), content).map_err(|source| {
+            DemoBundleError::WriteFailure {
+                path: directory.to_string_lossy().to_string(),
+                source,
+            }
+        })?;
+        Ok(manifest)
+    }
+}
+
+// The files gathered so far, by their path in the bundle.
+struct Bundle {
+    directory: PathBuf,
+    sources: BTreeMap<String, String>,
+}
+
+impl Bundle {
+    fn new(directory: &Path) -> Self {
+        Bundle {
+            directory: directory.to_path_buf(),
+            sources: BTreeMap::new(),
+        }
+    }
+
+    // A file used more than once is only added once, and files with the same name from
+    // different places are numbered.
+    fn bundle_path(&self, source: &str, directory: &str, file_name: &str) -> (String, bool) {
+        let same_source = self
+            .sources
+            .iter()
+            .find(|(path, s)| *s == source && path.starts_with(&format!("{}/", directory)));
+        if let Some((path, _)) = same_source {
+            return (path.to_string(), true);
+        }
+
+        let file_name = path_component(file_name);
+        let mut path = format!("{}/{}", directory, file_name);
+        let mut count = 1;
+        while self.sources.contains_key(&path) {
+            count += 1;
+            path = format!("{}/{}-{}", directory, count, file_name);
+        }
+        (path, false)
+    }
+
+    fn add_file(
+        &mut self,
+        source: &str,
+        directory: &str,
+        file_name: &str,
+    ) -> Result<String, DemoBundleError> {
+        let (path, bundled) = self.bundle_path(source, directory, file_name);
+        if bundled {
+            return Ok(path);
+        }
+
+        println!("\t\tAdding {} from {}", &path, source);
+        let target = self.directory.join(&path);
+        let failure = |message: String| DemoBundleError::NotAdded {
+            source_path: source.to_string(),
+            message,
+        };
+        fs::create_dir_all(target.parent().unwrap()).map_err(|e| failure(e.to_string()))?;
+        save_file(source, &target).map_err(|e| failure(e.to_string()))?;
+        self.sources.insert(path.clone(), source.to_string());
+        Ok(path)
+    }
+
+    fn add_repo(&mut self, url: &str, directory: &str) -> Result<String, DemoBundleError> {
+        let (path, bundled) = self.bundle_path(url, directory, &get_filename(url, ""));
+        if bundled {
+            return Ok(path);
+        }
+
+        println!("\t\tMirroring {} into {}", url, &path);
+        git_mirror(url, &self.directory.join(&path)).map_err(|e| DemoBundleError::NotAdded {
+            source_path: url.to_string(),
+            message: e.to_string(),
+        })?;
+        self.sources.insert(path.clone(), url.to_string());
+        Ok(path)
+    }
+}
+
+// Session and file names come from the configuration, so they are kept to a single path
+// component inside the bundle: separators are replaced and names made only of dots (like `..`)
+// become underscores.
+fn path_component(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "_-.".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.chars().all(|c| c == '.') {
+        "_".repeat(name.len().max(1))
+    } else {
+        name
+    }
+}
+
+fn staging_directory(output_path: &Path) -> PathBuf {
+    let file_name = get_filename(&output_path.to_string_lossy(), "");
+    output_path
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .join(format!(".{}.{}.staging", file_name, process::id()))
+}
+
+// Every file in the directory (the repositories too), with paths relative to it, in order.
+fn checksums(directory: &Path) -> Result<Vec<ManifestFile>, io::Error> {
+    let mut paths = Vec::new();
+    list_files(directory, &mut paths)?;
+    paths.sort();
+
+    let mut files = Vec::new();
+    for path in paths {
+        let mut hasher = Sha256::new();
+        let size = io::copy(&mut File::open(&path)?, &mut hasher)?;
+        let relative: Vec<String> = path
+            .strip_prefix(directory)
+            .unwrap()
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        files.push(ManifestFile {
+            path: relative.join("/"),
+            size,
+            sha256: format!("{:x}", hasher.finalize()),
+        });
+    }
+    Ok(files)
+}
+
+fn list_files(directory: &Path, paths: &mut Vec<PathBuf>) -> Result<(), io::Error> {
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            list_files(&path, paths)?;
+        } else {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+// The manifest comes first, so it can be read without unpacking the whole bundle.
+fn write_archive(directory: &Path, output_path: &Path) -> Result<(), io::Error> {
+    let encoder = zstd::Encoder::new(File::create(output_path)?, 0)?;
+    let mut archive = tar::Builder::new(encoder);
+    archive.append_path_with_name(directory.join(MANIFEST_FILE), MANIFEST_FILE)?;
+    archive.append_path_with_name(directory.join(CONFIG_FILE), CONFIG_FILE)?;
+    if directory.join(FILES_DIRECTORY).is_dir() {
+        archive.append_dir_all(FILES_DIRECTORY, directory.join(FILES_DIRECTORY))?;
+    }
+    archive.into_inner()?.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::read_config;
+    use crate::config::validation::validate;
+    use std::env;
+
+    fn unpack(name: &str, session_names: Option<Vec<&str>>) -> PathBuf {
+        let output = env::temp_dir().join(format!("{}.tar.zst", name));
+        let directory = env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&directory);
+        let mut bundle = BundleCommand::new(
+            "./test/artifacts/bundle_config.yml".to_string(),
+            output.to_string_lossy().to_string(),
+        );
+        bundle.event = Some("tour".to_string());
+        bundle.session_names =
+            session_names.map(|names| names.iter().map(|s| s.to_string()).collect());
+
+        bundle.execute().unwrap();
+        let decoder = zstd::Decoder::new(File::open(&output).unwrap()).unwrap();
+        tar::Archive::new(decoder).unpack(&directory).unwrap();
+        fs::remove_file(&output).unwrap();
+        directory
+    }
+
+    fn manifest(directory: &Path) -> Manifest {
+        let content = fs::read_to_string(directory.join(MANIFEST_FILE)).unwrap();
+        serde_json::from_str(&content).unwrap()
+    }
+
+    #[test]
+    fn bundles_point_at_the_bundled_files() {
+        let directory = unpack("demo-up-bundle-files", None);
+        let config = read_config(&directory.join(CONFIG_FILE).to_string_lossy()).unwrap();
+        let manifest = manifest(&directory);
+        fs::remove_dir_all(&directory).unwrap();
+
+        let sessions = config.sessions();
+        let template = sessions[0].commands()[0].template.clone().unwrap();
+        assert_eq!(
+            sessions[0].slides.clone().unwrap().url,
+            Some("files/SRE10/slides/SRE10.json".to_string())
+        );
+        assert_eq!(
+            sessions[0].videos.clone().unwrap()[0].url,
+            Some("files/SRE10/videos/parameters.json".to_string())
+        );
+        assert_eq!(
+            template.path,
+            Some("files/templates/demo_template.json".to_string())
+        );
+        assert_eq!(
+            template.parameters_file,
+            Some("files/templates/template_parameters.json".to_string())
+        );
+        assert_eq!(
+            sessions[1].commands()[0].template.clone().unwrap().path,
+            template.path
+        );
+        assert_eq!(manifest.sessions, vec!["SRE10", "SRE20"]);
+        assert_eq!(
+            manifest.sources["files/templates/demo_template.json"],
+            "./test/artifacts/demo_template.json"
+        );
+    }
+
+    #[test]
+    fn unpacked_bundles_are_valid() {
+        let directory = unpack("demo-up-bundle-valid", None);
+        let config = read_config(&directory.join(CONFIG_FILE).to_string_lossy()).unwrap();
+        let problems = validate(&config, &directory);
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert!(problems.is_empty(), "{:?}", problems);
+    }
+
+    #[test]
+    fn session_names_stay_inside_the_bundle() {
+        for name in &["../x", "..", ".", "", "/etc", "SRE10\\..\\x"] {
+            let path = format!("{}/{}", FILES_DIRECTORY, path_component(name));
+
+            assert_eq!(Path::new(&path).components().count(), 2, "{}", path);
+            assert!(Path::new(&path)
+                .components()
+                .all(|c| matches!(c, std::path::Component::Normal(_))));
+        }
+        assert_eq!(path_component("SRE10"), "SRE10");
+    }
+
+    #[test]
+    fn bundles_are_staged_next_to_the_output() {
+        let directory = staging_directory(Path::new("/srv/bundles/tour.tar.zst"));
+
+        assert_eq!(directory.parent(), Some(Path::new("/srv/bundles")));
+        assert!(get_filename(&directory.to_string_lossy(), "").starts_with(".tour.tar.zst."));
+    }
+
+    #[test]
+    fn manifests_have_the_checksum_of_every_file() {
+        let directory = unpack("demo-up-bundle-checksums", Some(vec!["SRE20"]));
+        let manifest = manifest(&directory);
+        let template = fs::read(directory.join("files/templates/demo_template.json")).unwrap();
+        let expected = fs::read("./test/artifacts/demo_template.json").unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+
+        let paths: Vec<&str> = manifest.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec!["demo.yml", "files/templates/demo_template.json"]
+        );
+        assert_eq!(manifest.sessions, vec!["SRE20"]);
+        assert_eq!(manifest.files[1].size, expected.len() as u64);
+        assert_eq!(
+            manifest.files[1].sha256,
+            format!("{:x}", Sha256::digest(&template))
+        );
+        assert_eq!(template, expected);
+    }
+
+    #[test]
+    fn files_with_the_same_name_are_numbered() {
+        let mut bundle = Bundle::new(Path::new("bundle"));
+        bundle.sources.insert(
+            "files/templates/azuredeploy.json".to_string(),
+            "https://example.com/a/azuredeploy.json".to_string(),
+        );
+
+        assert_eq!(
+            bundle.bundle_path(
+                "https://example.com/a/azuredeploy.json",
+                "files/templates",
+                "azuredeploy.json"
+            ),
+            ("files/templates/azuredeploy.json".to_string(), true)
+        );
+        assert_eq!(
+            bundle.bundle_path(
+                "https://example.com/b/azuredeploy.json",
+                "files/templates",
+                "azuredeploy.json"
+            ),
+            ("files/templates/2-azuredeploy.json".to_string(), false)
+        );
+    }
+}
@@ -87,9 +87,51 @@ pub fn get_subscription_arg<'a, 'b>() -> Arg<'a, 'b> {
     Arg::with_name("subscription")
         .long("azure-subscription")
         .short("a")
+        .help("Azure subscription name or id.")
         .takes_value(true)
 }
 
+pub fn get_tenant_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("tenant")
+        .long("tenant")
+        .short("t")
+        .help("Azure Active Directory tenant id or domain to log in to.")
+        .env("AZURE_TENANT_ID")
+        .takes_value(true)
+}
+
+pub fn get_client_id_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("client_id")
+        .long("client-id")
+        .help("Application (client) id of the service principal or user assigned managed identity to log in with.")
+        .env("AZURE_CLIENT_ID")
+        .takes_value(true)
+}
+
+pub fn get_client_secret_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("client_secret")
+        .long("client-secret")
+        .help("Client secret of the service principal to log in with.")
+        .env("AZURE_CLIENT_SECRET")
+        .hide_env_values(true)
+        .conflicts_with("client_certificate")
+        .takes_value(true)
+}
+
+pub fn get_client_certificate_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("client_certificate")
+        .long("client-certificate")
+        .help("Path to the PEM certificate of the service principal to log in with.")
+        .env("AZURE_CLIENT_CERTIFICATE_PATH")
+        .takes_value(true)
+}
+
+pub fn get_managed_identity_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("managed_identity")
+        .long("managed-identity")
+        .help("Log in with the managed identity of the machine running the command.")
+}
+
 pub fn get_event_arg<'a, 'b>() -> Arg<'a, 'b> {
     Arg::with_name("event")
         .long("event")
@@ -98,6 +140,43 @@ pub fn get_event_arg<'a, 'b>() -> Arg<'a, 'b> {
         .env(get_user_environment_variable())
 }
 
+pub fn get_location_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("location")
+        .long("location")
+        .help("Azure region for resource groups without a location in the configuration file.")
+        .takes_value(true)
+}
+
+pub fn get_profile_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("profile")
+        .long("profile")
+        .help("Profile from the configuration file to apply, like rehearsal or stage.")
+        .takes_value(true)
+}
+
+pub fn get_presenter_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("presenter")
+        .long("presenter")
+        .help("Presenter to tag the resource groups with.  Defaults to your local user name.")
+        .env(get_user_environment_variable())
+}
+
+pub fn get_expires_in_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("expires_in")
+        .long("expires-in")
+        .help("Hours until the resource groups are considered expired.")
+        .validator(is_number)
+        .default_value("72")
+        .takes_value(true)
+}
+
+pub fn is_number(value: String) -> Result<(), String> {
+    value
+        .parse::<u32>()
+        .map(|_| ())
+        .map_err(|_| format!("{} is not a whole number.", value))
+}
+
 pub fn get_learning_path_arg<'a, 'b>() -> Arg<'a, 'b> {
     Arg::with_name("learning_path")
         .multiple(true)
@@ -128,6 +207,62 @@ pub fn get_exclude_arg<'a, 'b>() -> Arg<'a, 'b> {
         .takes_value(true)
 }
 
+pub fn get_what_if_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("what_if")
+        .long("what-if")
+        .help("Preview the changes each ARM template would make and show the Azure CLI commands, without changing anything.")
+}
+
+pub fn get_confirm_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("confirm")
+        .long("confirm")
+        .help("Ask for confirmation after a what-if preview before deploying the template or running the command.")
+}
+
+pub fn get_azure_profile_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("azure_profile")
+        .long("azure-profile")
+        .help("Name of the isolated Azure CLI profile to use.  Defaults to the event name.")
+        .conflicts_with("shared_azure_config")
+        .takes_value(true)
+}
+
+pub fn get_shared_azure_config_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("shared_azure_config")
+        .long("shared-azure-config")
+        .help("Use your own Azure CLI configuration instead of an isolated profile.")
+}
+
+pub fn get_config_sha256_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("config_sha256")
+        .long("config-sha256")
+        .help("Expected SHA-256 of the configuration file, which is not used if it differs.")
+        .takes_value(true)
+}
+
+pub fn get_config_public_key_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("config_public_key")
+        .long("config-public-key")
+        .help("Minisign public key (or public key file) to verify the signatures of remote configuration files with.")
+        .env("DEMO_CONFIG_PUBLIC_KEY")
+        .takes_value(true)
+}
+
+pub fn get_allow_unsigned_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("allow_unsigned")
+        .long("allow-unsigned")
+        .help("Use unsigned remote configuration files without asking for confirmation.")
+}
+
+pub fn get_backend_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("backend")
+        .long("backend")
+        .help("How to provision ARM templates: through the Azure CLI or directly against the Azure Resource Manager REST API.")
+        .possible_values(&["az", "rest"])
+        .default_value("az")
+        .takes_value(true)
+}
+
 pub fn get_output_arg<'a, 'b>() -> Arg<'a, 'b> {
     Arg::with_name("OUTPUT")
         .help("Path to write the local configuration file to use.")
@@ -0,0 +1,53 @@
+use super::args::*;
+use clap::{App, Arg};
+
+pub fn get_bundle_subcommand<'a, 'b>() -> App<'a, 'b> {
+    App::new("bundle")
+        .about("Packages the configuration, slides, videos, repositories and templates for one or more learning paths or sessions into a single archive, for use without downloads.")
+        .arg(get_config_file_arg())
+        .arg(get_config_sha256_arg())
+        .arg(get_config_public_key_arg())
+        .arg(get_allow_unsigned_arg())
+        .arg(get_event_arg())
+        .arg(get_learning_path_arg())
+        .arg(get_session_name_arg())
+        .arg(get_profile_arg())
+        .arg(get_bundle_output_arg())
+}
+
+fn get_bundle_output_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("output")
+        .long("output")
+        .short("o")
+        .help("Path to write the bundle to.")
+        .takes_value(true)
+        .default_value("./demo.tar.zst")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demo_bundle_for_sessions() {
+        let args = vec![
+            "bundle",
+            "--session-name",
+            "SRE10",
+            "SRE20",
+            "-o",
+            "event.tar.zst",
+        ];
+
+        let matches = get_bundle_subcommand().get_matches_from(args);
+
+        assert_eq!(matches.value_of("output"), Some("event.tar.zst"));
+        assert_eq!(
+            matches
+                .values_of("session_name")
+                .unwrap()
+                .collect::<Vec<_>>(),
+            vec!["SRE10", "SRE20"]
+        );
+    }
+}
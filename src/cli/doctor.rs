@@ -0,0 +1,34 @@
+use super::args::*;
+use clap::App;
+
+pub fn get_doctor_subcommand<'a, 'b>() -> App<'a, 'b> {
+    App::new("doctor")
+        .about("Checks that the local environment is ready to set up the demo environment.")
+        .arg(get_subscription_arg())
+        .arg(get_config_file_arg())
+        .arg(get_event_arg())
+        .arg(get_azure_profile_arg())
+        .arg(get_shared_azure_config_arg())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demo_doctor_with_subscription_and_profile() {
+        let args = vec![
+            "doctor",
+            "--azure-subscription",
+            "Ignite the Tour",
+            "--azure-profile",
+            "rehearsal",
+        ];
+
+        let matches = get_doctor_subcommand().get_matches_from(args);
+
+        assert_eq!(matches.value_of("subscription"), Some("Ignite the Tour"));
+        assert_eq!(matches.value_of("azure_profile"), Some("rehearsal"));
+        assert!(!matches.is_present("shared_azure_config"));
+    }
+}
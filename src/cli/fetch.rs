@@ -1,9 +1,37 @@
-use super::args::{get_config_file_arg, get_output_arg};
+use super::args::*;
 use clap::App;
 
 pub fn get_fetch_subcommand<'a, 'b>() -> App<'a, 'b> {
     App::new("fetch")
         .about("Retrieves a local copy of a configuration file for the demo environment for one or more learning paths or sessions.")
         .arg(get_config_file_arg())
+        .arg(get_config_sha256_arg())
+        .arg(get_config_public_key_arg())
+        .arg(get_allow_unsigned_arg())
+        .arg(get_event_arg())
+        .arg(get_learning_path_arg())
+        .arg(get_session_name_arg())
+        .arg(get_profile_arg())
         .arg(get_output_arg())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demo_fetch_for_sessions() {
+        let args = vec!["fetch", "./sre.yml", "--session-name", "SRE10", "SRE20"];
+
+        let matches = get_fetch_subcommand().get_matches_from(args);
+
+        assert_eq!(matches.value_of("OUTPUT"), Some("./sre.yml"));
+        assert_eq!(
+            matches
+                .values_of("session_name")
+                .unwrap()
+                .collect::<Vec<_>>(),
+            vec!["SRE10", "SRE20"]
+        );
+    }
+}
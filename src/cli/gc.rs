@@ -0,0 +1,81 @@
+use super::args::*;
+use clap::{App, Arg};
+
+pub fn get_gc_subcommand<'a, 'b>() -> App<'a, 'b> {
+    App::new("gc")
+        .about("Deletes the expired resource groups created for demo environments.")
+        .arg(get_subscription_arg())
+        .arg(get_tenant_arg())
+        .arg(get_client_id_arg())
+        .arg(get_client_secret_arg())
+        .arg(get_client_certificate_arg())
+        .arg(get_managed_identity_arg())
+        .arg(get_event_arg())
+        .arg(get_backend_arg())
+        .arg(get_azure_profile_arg())
+        .arg(get_shared_azure_config_arg())
+        .arg(get_yes_arg())
+        .arg(get_dry_run_arg())
+        .arg(get_older_than_arg())
+}
+
+fn get_yes_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("yes")
+        .long("yes")
+        .short("y")
+        .help("Delete the expired resource groups without asking for confirmation.")
+}
+
+fn get_dry_run_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("dry_run")
+        .long("dry-run")
+        .help("Show the expired resource groups without deleting them.")
+        .conflicts_with("yes")
+}
+
+fn get_older_than_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("older_than")
+        .long("older-than")
+        .help("Treat resource groups created more than this many hours ago as expired, whatever their expiry.")
+        .validator(is_number)
+        .takes_value(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demo_gc_no_parameters() {
+        let matches = get_gc_subcommand().get_matches_from(vec!["gc"]);
+
+        assert!(!matches.is_present("yes"));
+        assert!(!matches.is_present("dry_run"));
+        assert!(matches.value_of("older_than").is_none());
+    }
+
+    #[test]
+    fn demo_gc_event_is_only_given_on_the_command_line() {
+        let matches = get_gc_subcommand().get_matches_from(vec!["gc"]);
+        assert_eq!(matches.occurrences_of("event"), 0);
+
+        let matches = get_gc_subcommand().get_matches_from(vec!["gc", "--event", "tour"]);
+        assert_eq!(matches.occurrences_of("event"), 1);
+        assert_eq!(matches.value_of("event"), Some("tour"));
+    }
+
+    #[test]
+    fn demo_gc_dry_run_conflicts_with_yes() {
+        let matches = get_gc_subcommand().get_matches_from_safe(vec!["gc", "--yes", "--dry-run"]);
+
+        assert!(matches.is_err());
+    }
+
+    #[test]
+    fn demo_gc_older_than_must_be_a_number() {
+        let matches =
+            get_gc_subcommand().get_matches_from_safe(vec!["gc", "--older-than", "a week"]);
+
+        assert!(matches.is_err());
+    }
+}
@@ -0,0 +1,11 @@
+use clap::{App, Arg};
+
+pub fn get_migrate_subcommand<'a, 'b>() -> App<'a, 'b> {
+    App::new("migrate")
+        .about("Rewrites a local configuration file in the newest format, keeping the original as a backup.")
+        .arg(
+            Arg::with_name("CONFIG_FILE")
+                .help("Path to the local configuration file to migrate.")
+                .default_value("./demo.yml"),
+        )
+}
@@ -1,21 +1,53 @@
 mod args;
+mod bundle;
+mod doctor;
 mod fetch;
+mod gc;
+mod migrate;
 mod pkg;
+mod plan;
+mod schema;
+mod status;
 mod up;
+mod validate;
 
+use self::bundle::get_bundle_subcommand;
+use self::doctor::get_doctor_subcommand;
 use self::fetch::get_fetch_subcommand;
+use self::gc::get_gc_subcommand;
+use self::migrate::get_migrate_subcommand;
 use self::pkg::get_pkg_subcommand;
+use self::plan::get_plan_subcommand;
+use self::schema::get_schema_subcommand;
+use self::status::get_status_subcommand;
 use self::up::get_up_subcommand;
+use self::validate::get_validate_subcommand;
 
-use clap::App;
+use clap::{App, Arg};
 
 pub fn get_app_cli<'a, 'b>(version: &'b str) -> App<'a, 'b> {
     App::new("demo")
         .version(&*version)
         .author("Steven Murawski <steven.murawski@microsoft.com>")
         .about("Sets up or tears down demo environments for Microsoft Ignite | The Tour")
+        .arg(get_offline_arg())
         .subcommand(get_up_subcommand())
         .subcommand(get_pkg_subcommand())
         .subcommand(get_fetch_subcommand())
+        .subcommand(get_bundle_subcommand())
+        .subcommand(get_doctor_subcommand())
+        .subcommand(get_gc_subcommand())
+        .subcommand(get_plan_subcommand())
+        .subcommand(get_status_subcommand())
+        .subcommand(get_validate_subcommand())
+        .subcommand(get_schema_subcommand())
+        .subcommand(get_migrate_subcommand())
     // .subcommand(get_down_subcommand())
 }
+
+fn get_offline_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("offline")
+        .long("offline")
+        .global(true)
+        .help("Use cached copies of remote configuration files instead of downloading them.")
+}
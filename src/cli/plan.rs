@@ -0,0 +1,29 @@
+use super::args::*;
+use clap::App;
+
+pub fn get_plan_subcommand<'a, 'b>() -> App<'a, 'b> {
+    App::new("plan")
+        .about("Shows what up would set up for one or more learning paths or sessions, without changing anything.")
+        .arg(get_subscription_arg())
+        .arg(get_config_file_arg())
+        .arg(get_event_arg())
+        .arg(get_learning_path_arg())
+        .arg(get_session_name_arg())
+        .arg(get_profile_arg())
+        .arg(get_location_arg())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demo_plan_with_a_profile() {
+        let args = vec!["plan", "--session-name", "SRE10", "--profile", "rehearsal"];
+
+        let matches = get_plan_subcommand().get_matches_from(args);
+
+        assert_eq!(matches.value_of("profile"), Some("rehearsal"));
+        assert_eq!(matches.value_of("session_name"), Some("SRE10"));
+    }
+}
@@ -0,0 +1,7 @@
+use clap::App;
+
+pub fn get_schema_subcommand<'a, 'b>() -> App<'a, 'b> {
+    App::new("schema").about(
+        "Prints a JSON Schema for the configuration file, for editor completion and validation.",
+    )
+}
@@ -0,0 +1,37 @@
+use super::args::*;
+use clap::{App, Arg};
+
+pub fn get_status_subcommand<'a, 'b>() -> App<'a, 'b> {
+    App::new("status")
+        .about("Shows the local and cloud state of the demo environment for one or more learning paths or sessions.")
+        .arg(get_subscription_arg())
+        .arg(get_config_file_arg())
+        .arg(get_event_arg())
+        .arg(get_learning_path_arg())
+        .arg(get_session_name_arg())
+        .arg(get_profile_arg())
+        .arg(get_azure_profile_arg())
+        .arg(get_shared_azure_config_arg())
+        .arg(get_json_arg())
+}
+
+fn get_json_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("json")
+        .long("json")
+        .help("Write the status as JSON.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demo_status_for_a_session_as_json() {
+        let args = vec!["status", "--session-name", "SRE10", "--json"];
+
+        let matches = get_status_subcommand().get_matches_from(args);
+
+        assert_eq!(matches.value_of("session_name"), Some("SRE10"));
+        assert!(matches.is_present("json"));
+    }
+}
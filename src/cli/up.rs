@@ -1,15 +1,41 @@
 use super::args::*;
-use clap::App;
+use clap::{App, Arg};
 
 pub fn get_up_subcommand<'a, 'b>() -> App<'a, 'b> {
     App::new("up")
         .about("Sets up the demo environment for one or more learning paths or sessions.")
         .arg(get_subscription_arg())
+        .arg(get_tenant_arg())
+        .arg(get_client_id_arg())
+        .arg(get_client_secret_arg())
+        .arg(get_client_certificate_arg())
+        .arg(get_managed_identity_arg())
         .arg(get_config_file_arg())
+        .arg(get_config_sha256_arg())
+        .arg(get_config_public_key_arg())
+        .arg(get_allow_unsigned_arg())
         .arg(get_event_arg())
         .arg(get_learning_path_arg())
         .arg(get_session_name_arg())
         .arg(get_exclude_arg())
+        .arg(get_profile_arg())
+        .arg(get_location_arg())
+        .arg(get_allow_unknown_location_arg())
+        .arg(get_presenter_arg())
+        .arg(get_expires_in_arg())
+        .arg(get_what_if_arg())
+        .arg(get_confirm_arg())
+        .arg(get_backend_arg())
+        .arg(get_azure_profile_arg())
+        .arg(get_shared_azure_config_arg())
+}
+
+fn get_allow_unknown_location_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("allow_unknown_location")
+        .long("allow-unknown-location")
+        .help(
+            "Use locations that are not known Azure regions, like regions newer than this release.",
+        )
 }
 
 #[cfg(test)]
@@ -75,6 +101,51 @@ mod tests {
         assert_eq!(subscription.unwrap(), "your_azure_subscription");
     }
 
+    #[test]
+    fn demo_with_tenant() {
+        let args = vec!["up", "--tenant", "contoso.onmicrosoft.com"];
+
+        let cli = get_up_subcommand();
+        let matches = cli.get_matches_from(args);
+
+        assert_eq!(matches.value_of("tenant"), Some("contoso.onmicrosoft.com"));
+    }
+
+    #[test]
+    fn demo_with_service_principal() {
+        let args = vec![
+            "up",
+            "--client-id",
+            "app",
+            "--client-secret",
+            "secret",
+            "--tenant",
+            "contoso.onmicrosoft.com",
+        ];
+
+        let cli = get_up_subcommand();
+        let matches = cli.get_matches_from(args);
+
+        assert_eq!(matches.value_of("client_id"), Some("app"));
+        assert_eq!(matches.value_of("client_secret"), Some("secret"));
+    }
+
+    #[test]
+    fn demo_secret_conflicts_with_certificate() {
+        let args = vec![
+            "up",
+            "--client-secret",
+            "secret",
+            "--client-certificate",
+            "app.pem",
+        ];
+
+        let cli = get_up_subcommand();
+        let matches = cli.get_matches_from_safe(args);
+
+        assert!(matches.is_err());
+    }
+
     #[test]
     fn demo_up_only_learning_path() {
         let args = vec!["up", "--learning-path", "DAT"];
@@ -117,6 +188,69 @@ mod tests {
         assert!(session_name.is_some());
     }
 
+    #[test]
+    fn demo_up_with_what_if_and_confirm() {
+        let args = vec!["up", "--what-if", "--confirm"];
+
+        let cli = get_up_subcommand();
+        let matches = cli.get_matches_from(args);
+
+        assert!(matches.is_present("what_if"));
+        assert!(matches.is_present("confirm"));
+    }
+
+    #[test]
+    fn demo_up_with_unknown_locations() {
+        let cli = get_up_subcommand();
+        let matches = cli.get_matches_from(vec!["up"]);
+        assert!(!matches.is_present("allow_unknown_location"));
+
+        let cli = get_up_subcommand();
+        let matches = cli.get_matches_from(vec!["up", "--allow-unknown-location"]);
+        assert!(matches.is_present("allow_unknown_location"));
+    }
+
+    #[test]
+    fn demo_up_defaults_to_az_backend() {
+        let args = vec!["up"];
+
+        let cli = get_up_subcommand();
+        let matches = cli.get_matches_from(args);
+
+        assert_eq!(matches.value_of("backend").unwrap(), "az");
+    }
+
+    #[test]
+    fn demo_up_rejects_unknown_backend() {
+        let args = vec!["up", "--backend", "terraform"];
+
+        let cli = get_up_subcommand();
+        let matches = cli.get_matches_from_safe(args);
+
+        assert!(matches.is_err());
+    }
+
+    #[test]
+    fn demo_up_profile_conflicts_with_shared_config() {
+        let args = vec!["up", "--azure-profile", "tour", "--shared-azure-config"];
+
+        let cli = get_up_subcommand();
+        let matches = cli.get_matches_from_safe(args);
+
+        assert!(matches.is_err());
+    }
+
+    #[test]
+    fn demo_up_expires_in_must_be_a_number() {
+        let cli = get_up_subcommand();
+        let matches = cli.get_matches_from(vec!["up"]);
+        assert_eq!(matches.value_of("expires_in"), Some("72"));
+
+        let cli = get_up_subcommand();
+        let matches = cli.get_matches_from_safe(vec!["up", "--expires-in", "soon"]);
+        assert!(matches.is_err());
+    }
+
     // #[test]
     // fn demo_up_learning_path_and_session_error() {
     //     let cli = get_up_subcommand();
@@ -0,0 +1,8 @@
+use super::args::get_config_file_arg;
+use clap::App;
+
+pub fn get_validate_subcommand<'a, 'b>() -> App<'a, 'b> {
+    App::new("validate")
+        .about("Checks a configuration file for mistakes before using it.")
+        .arg(get_config_file_arg())
+}
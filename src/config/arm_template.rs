@@ -1,11 +1,34 @@
+use super::{read, read_from_url};
+use custom_error::custom_error;
+use reqwest::Url;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::collections::HashMap;
+use std::path::Path;
 
-#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+const PARAMETERS_SCHEMA: &str =
+    "https://schema.management.azure.com/schemas/2015-01-01/deploymentParameters.json#";
+
+custom_error! {
+    pub ParametersFileError
+    ReadFailure{source: std::io::Error} = "Failed to read the parameters file.",
+    ParseFailure{source: serde_json::Error} = "Failed to parse the template parameters.",
+    MissingParameters = "The parameters file does not contain a parameters object.",
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct ArmTemplate {
+    /// A local template file.  A template has either a path or a url.
     pub path: Option<String>,
+    /// A remote template file.  A template has either a path or a url.
     pub url: Option<String>,
-    pub parameters: Option<HashMap<String, String>>,
+    /// Template parameters, taking precedence over the parameters file.
+    #[schemars(with = "Option<HashMap<String, serde_json::Value>>")]
+    pub parameters: Option<HashMap<String, serde_yaml::Value>>,
+    /// A local or remote ARM deployment parameters file.
+    pub parameters_file: Option<String>,
 }
 
 impl ArmTemplate {
@@ -27,16 +50,45 @@ impl ArmTemplate {
         }
     }
 
-    pub fn parameters(&self) -> Vec<String> {
-        let local_template = self.clone();
-        let mut parameters = Vec::new();
-        if let Some(p) = local_template.parameters {
-            for (k, v) in &p {
-                let parameter_string = format!("{}={}", k, v);
-                parameters.push(parameter_string);
+    pub fn has_parameters(&self) -> bool {
+        self.parameters.is_some() || self.parameters_file.is_some()
+    }
+
+    // Builds an ARM deployment parameters document from the parameters file (if any), with the
+    // inline parameters taking precedence.
+    pub fn parameters(&self) -> Result<serde_json::Value, ParametersFileError> {
+        let mut parameters = match self.parameters_file {
+            Some(ref file) => read_parameters_file(file)?,
+            None => serde_json::Map::new(),
+        };
+
+        if let Some(ref inline) = self.parameters {
+            for (k, v) in inline {
+                let value = serde_json::to_value(v)?;
+                parameters.insert(k.to_string(), json!({ "value": value }));
             }
         }
-        parameters
+
+        Ok(json!({
+            "$schema": PARAMETERS_SCHEMA,
+            "contentVersion": "1.0.0.0",
+            "parameters": parameters,
+        }))
+    }
+}
+
+fn read_parameters_file(
+    file: &str,
+) -> Result<serde_json::Map<String, serde_json::Value>, ParametersFileError> {
+    let content = match Url::parse(file) {
+        Ok(url) => read_from_url(url)?,
+        Err(_) => read(Path::new(file))?,
+    };
+
+    let document: serde_json::Value = serde_json::from_str(&content)?;
+    match document["parameters"].as_object() {
+        Some(p) => Ok(p.clone()),
+        None => Err(ParametersFileError::MissingParameters),
     }
 }
 
@@ -46,6 +98,66 @@ impl Default for ArmTemplate {
             path: None,
             url: None,
             parameters: None,
+            parameters_file: None,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template_from_yaml(yaml: &str) -> ArmTemplate {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn inline_parameters_keep_their_types() {
+        let template = template_from_yaml(
+            "
+parameters:
+  webAppName: demo up=test
+  instances: 3
+  alwaysOn: true
+  tags:
+    event: tour
+  ports: [80, 443]
+",
+        );
+        let parameters = template.parameters().unwrap();
+
+        assert_eq!(
+            parameters["parameters"]["webAppName"]["value"],
+            "demo up=test"
+        );
+        assert_eq!(parameters["parameters"]["instances"]["value"], 3);
+        assert_eq!(parameters["parameters"]["alwaysOn"]["value"], true);
+        assert_eq!(parameters["parameters"]["tags"]["value"]["event"], "tour");
+        assert_eq!(parameters["parameters"]["ports"]["value"][1], 443);
+    }
+
+    #[test]
+    fn inline_parameters_override_parameters_file() {
+        let template = template_from_yaml(
+            "
+parameters_file: ./test/artifacts/template_parameters.json
+parameters:
+  sku: B1
+",
+        );
+        let parameters = template.parameters().unwrap();
+
+        assert_eq!(parameters["parameters"]["webAppName"]["value"], "demoup");
+        assert_eq!(parameters["parameters"]["sku"]["value"], "B1");
+    }
+
+    #[test]
+    fn parameters_file_must_contain_parameters() {
+        let template = ArmTemplate {
+            parameters_file: Some("./test/artifacts/environment.json".to_string()),
+            ..Default::default()
+        };
+
+        assert!(template.parameters().is_err());
+    }
+}
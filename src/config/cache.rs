@@ -0,0 +1,312 @@
+use super::verify::sha256;
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::{Client, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Set once from --offline, so every remote read (configuration files, includes and parameters
+// files) is served from the cache.
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+// The part of the file name taken from the URL, after the hash.
+const MAX_FILE_NAME_LENGTH: usize = 64;
+
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::SeqCst);
+}
+
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::SeqCst)
+}
+
+pub fn cache_directory() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(env::temp_dir)
+        .join("demo-up")
+        .join("configs")
+}
+
+// What is needed to revalidate a cached download.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct CacheEntry {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+// Downloads through the cache: a cached copy is revalidated with its ETag and Last-Modified
+// date, and used with a warning when the server cannot be reached.  Offline, the network is
+// never used.
+pub fn read_cached(url: Url, directory: &Path, offline: bool) -> Result<String, io::Error> {
+    let name = cache_name(&url);
+    let content_path = directory.join(format!("{}.yml", name));
+    let entry_path = directory.join(format!("{}.json", name));
+    let entry = read_entry(&entry_path).filter(|_| content_path.is_file());
+
+    if offline {
+        return match entry {
+            Some(_) => fs::read_to_string(&content_path),
+            None => Err(io::Error::other(format!(
+                "{} is not cached, run without --offline once to cache it",
+                url
+            ))),
+        };
+    }
+
+    match download(&url, entry.as_ref()) {
+        Ok(Download::NotModified) => fs::read_to_string(&content_path),
+        Ok(Download::Modified(content, new_entry)) => {
+            // Failing to cache is not a reason to fail the download.
+            let _ = write_entry(directory, &content_path, &content, &entry_path, &new_entry);
+            Ok(content)
+        }
+        Err(e) => match entry {
+            Some(_) => {
+                eprintln!(
+                    "Warning: failed to download {} ({}), using the cached copy.",
+                    url, e
+                );
+                fs::read_to_string(&content_path)
+            }
+            None => Err(e),
+        },
+    }
+}
+
+enum Download {
+    NotModified,
+    Modified(String, CacheEntry),
+}
+
+fn download(url: &Url, entry: Option<&CacheEntry>) -> Result<Download, io::Error> {
+    let client = Client::new();
+    let mut request = client.get(url.clone());
+    if let Some(e) = entry {
+        if let Some(ref etag) = e.etag {
+            request = request.header(IF_NONE_MATCH, etag.as_str());
+        }
+        if let Some(ref date) = e.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, date.as_str());
+        }
+    }
+
+    let mut resp = request.send().map_err(io::Error::other)?;
+    if resp.status() == StatusCode::NOT_MODIFIED && entry.is_some() {
+        return Ok(Download::NotModified);
+    }
+    if !resp.status().is_success() {
+        return Err(io::Error::other(format!(
+            "the server responded with {}",
+            resp.status()
+        )));
+    }
+
+    let header = |name| {
+        resp.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+    };
+    let new_entry = CacheEntry {
+        url: url.to_string(),
+        etag: header(ETAG),
+        last_modified: header(LAST_MODIFIED),
+    };
+    let mut content = String::new();
+    resp.read_to_string(&mut content)?;
+    Ok(Download::Modified(content, new_entry))
+}
+
+// Files are named by a hash of the whole URL, so different URLs never share a file and long
+// URLs still make short names, followed by the file name from the URL to find them by hand.
+fn cache_name(url: &Url) -> String {
+    let file_name: String = url
+        .path_segments()
+        .and_then(|mut s| s.rfind(|s| !s.is_empty()))
+        .unwrap_or_default()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(MAX_FILE_NAME_LENGTH)
+        .collect();
+    format!("{}-{}", sha256(url.as_str()), file_name)
+}
+
+fn read_entry(path: &Path) -> Option<CacheEntry> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn write_entry(
+    directory: &Path,
+    content_path: &Path,
+    content: &str,
+    entry_path: &Path,
+    entry: &CacheEntry,
+) -> Result<(), io::Error> {
+    fs::create_dir_all(directory)?;
+    fs::write(content_path, content)?;
+    fs::write(entry_path, serde_json::to_string_pretty(entry)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufReader;
+    use std::net::TcpListener;
+    use std::thread;
+    use std::thread::JoinHandle;
+
+    // Answers each connection with the next canned response (status, extra headers, body) and
+    // records the conditional headers of each request.
+    fn mock_server(
+        responses: Vec<(u16, &'static str, &'static str)>,
+    ) -> (Url, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!(
+            "http://{}/demo.yml",
+            listener.local_addr().unwrap()
+        ))
+        .unwrap();
+
+        let server = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for (status, headers, body) in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut conditions = Vec::new();
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    let header = header.trim().to_string();
+                    if header.is_empty() {
+                        break;
+                    }
+                    if header.to_lowercase().starts_with("if-") {
+                        conditions.push(header);
+                    }
+                }
+                requests.push(conditions.join(", "));
+
+                let response = format!(
+                    "HTTP/1.1 {} Mock\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    headers,
+                    body.len(),
+                    body
+                );
+                reader.get_mut().write_all(response.as_bytes()).unwrap();
+            }
+            requests
+        });
+
+        (url, server)
+    }
+
+    fn empty_cache(name: &str) -> PathBuf {
+        let directory = env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&directory);
+        directory
+    }
+
+    #[test]
+    fn cached_configs_are_revalidated() {
+        let directory = empty_cache("demo-up-cache-revalidate");
+        let (url, server) = mock_server(vec![
+            (
+                200,
+                "ETag: \"v1\"\r\nLast-Modified: Wed, 21 Oct 2026 07:28:00 GMT\r\n",
+                "location: westus2",
+            ),
+            (304, "", ""),
+        ]);
+
+        let first = read_cached(url.clone(), &directory, false).unwrap();
+        let second = read_cached(url, &directory, false).unwrap();
+        let requests = server.join().unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(first, "location: westus2");
+        assert_eq!(second, "location: westus2");
+        assert_eq!(requests[0], "");
+        assert_eq!(
+            requests[1].to_lowercase(),
+            "if-none-match: \"v1\", if-modified-since: wed, 21 oct 2026 07:28:00 gmt"
+        );
+    }
+
+    #[test]
+    fn the_cached_copy_is_used_when_the_download_fails() {
+        let directory = empty_cache("demo-up-cache-fallback");
+        let (url, server) = mock_server(vec![
+            (200, "ETag: \"v1\"\r\n", "location: westus2"),
+            (503, "", ""),
+        ]);
+
+        read_cached(url.clone(), &directory, false).unwrap();
+        let fallback = read_cached(url, &directory, false).unwrap();
+        server.join().unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(fallback, "location: westus2");
+    }
+
+    #[test]
+    fn error_pages_are_not_read_as_configs() {
+        let directory = empty_cache("demo-up-cache-not-found");
+        let (url, server) = mock_server(vec![(
+            404,
+            "Content-Type: text/html\r\n",
+            "<html>Not Found</html>",
+        )]);
+
+        let result = read_cached(url.clone(), &directory, false);
+        server.join().unwrap();
+        let cached = read_cached(url, &directory, true);
+        let _ = fs::remove_dir_all(&directory);
+
+        assert!(result.unwrap_err().to_string().contains("404"));
+        assert!(cached.is_err());
+    }
+
+    #[test]
+    fn offline_reads_never_use_the_network() {
+        let directory = empty_cache("demo-up-cache-offline");
+        let (url, server) = mock_server(vec![(200, "", "location: westus2")]);
+
+        assert!(read_cached(url.clone(), &directory, true).is_err());
+        read_cached(url.clone(), &directory, false).unwrap();
+        server.join().unwrap();
+        let offline = read_cached(url, &directory, true).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(offline, "location: westus2");
+    }
+
+    #[test]
+    fn cache_names_are_readable() {
+        let url = Url::parse("https://aka.ms/demo-up").unwrap();
+
+        assert!(cache_name(&url).ends_with("-demo-up"));
+    }
+
+    #[test]
+    fn cache_names_are_unique_and_short() {
+        let dashed = Url::parse("https://a/b-c.yml").unwrap();
+        let nested = Url::parse("https://a/b/c.yml").unwrap();
+        let long = Url::parse(&format!("https://a/{}.yml", "c".repeat(1000))).unwrap();
+
+        assert_ne!(cache_name(&dashed), cache_name(&nested));
+        assert!(cache_name(&long).len() <= 64 + 1 + MAX_FILE_NAME_LENGTH);
+    }
+}
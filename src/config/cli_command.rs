@@ -1,36 +1,94 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde_yaml::Value;
+use std::collections::BTreeMap;
 
-#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct CliCommand {
+    /// The az subcommand, such as `webapp restart`.
     pub subcommand: Option<String>,
-    pub parameters: Option<HashMap<String, String>>,
+    /// Options as a map, or a list of verbatim arguments and option maps.  An option set to null
+    /// (`~`) is a valueless switch.
+    pub parameters: Option<CliParameters>,
+}
+
+// Parameters are either a map of options (the original form) or an ordered list mixing
+// verbatim arguments (positionals or flags like `--yes`) with single option maps, which
+// allows the same option to be repeated.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(untagged)]
+pub enum CliParameters {
+    List(Vec<CliArgument>),
+    Map(#[schemars(with = "BTreeMap<String, serde_json::Value>")] BTreeMap<String, Value>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(untagged)]
+pub enum CliArgument {
+    Verbatim(String),
+    Options(#[schemars(with = "BTreeMap<String, serde_json::Value>")] BTreeMap<String, Value>),
 }
 
 impl CliCommand {
-    pub fn subcommand(&self) -> String {
+    pub fn subcommand(&self) -> Vec<String> {
         let cli_command = self.clone();
         if let Some(s) = cli_command.subcommand {
-            s
+            s.split_whitespace().map(|s| s.to_string()).collect()
         } else {
             panic!("Azure CLI commands must contain a subcommand.")
         }
     }
+
     pub fn parameters(&self) -> Vec<String> {
         let cli_command = self.clone();
         let mut parameters = Vec::new();
-        if let Some(p) = cli_command.parameters {
-            for (k, v) in &p {
-                let parameter = format!("--{}", k);
-                let argument = v.to_string();
-                parameters.push(parameter);
-                parameters.push(argument);
+        match cli_command.parameters {
+            Some(CliParameters::Map(p)) => parameters.extend(options(&p)),
+            Some(CliParameters::List(l)) => {
+                for argument in l {
+                    match argument {
+                        CliArgument::Verbatim(a) => parameters.push(a),
+                        CliArgument::Options(p) => parameters.extend(options(&p)),
+                    }
+                }
             }
+            None => {}
         }
         parameters
     }
 }
 
+fn options(parameters: &BTreeMap<String, Value>) -> Vec<String> {
+    let mut arguments = Vec::new();
+    for (k, v) in parameters {
+        let parameter = format!("--{}", k);
+        match v {
+            // Valueless switches such as `--yes` or `--no-wait`.  Booleans are values, like
+            // `--https-only false`, as they always were.
+            Value::Null => arguments.push(parameter),
+            Value::Sequence(values) => {
+                arguments.push(parameter);
+                arguments.extend(values.iter().map(argument));
+            }
+            _ => {
+                arguments.push(parameter);
+                arguments.push(argument(v));
+            }
+        }
+    }
+    arguments
+}
+
+fn argument(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        _ => serde_json::to_string(value).unwrap(),
+    }
+}
+
 impl Default for CliCommand {
     fn default() -> Self {
         CliCommand {
@@ -39,3 +97,95 @@ impl Default for CliCommand {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cli_command_from_yaml(yaml: &str) -> CliCommand {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn subcommand_is_split_into_arguments() {
+        let command = cli_command_from_yaml("subcommand: webapp  restart");
+
+        assert_eq!(command.subcommand(), vec!["webapp", "restart"]);
+    }
+
+    #[test]
+    fn map_parameters_are_still_supported() {
+        let command = cli_command_from_yaml(
+            "
+subcommand: webapp restart
+parameters:
+  name: demoup-test2
+  resource-group: SRE10
+",
+        );
+
+        assert_eq!(
+            command.parameters(),
+            vec!["--name", "demoup-test2", "--resource-group", "SRE10"]
+        );
+    }
+
+    #[test]
+    fn map_parameters_support_flags_and_multiple_values() {
+        let command = cli_command_from_yaml(
+            "
+subcommand: group delete
+parameters:
+  yes: ~
+  no-wait: ~
+  https-only: false
+  tags: [event=tour, owner=demo]
+  count: 2
+",
+        );
+
+        assert_eq!(
+            command.parameters(),
+            vec![
+                "--count",
+                "2",
+                "--https-only",
+                "false",
+                "--no-wait",
+                "--tags",
+                "event=tour",
+                "owner=demo",
+                "--yes"
+            ]
+        );
+    }
+
+    #[test]
+    fn list_parameters_keep_order_and_allow_repeats() {
+        let command = cli_command_from_yaml(
+            "
+subcommand: vm open-port
+parameters:
+  - myvm
+  - port: 80
+  - priority: 900
+  - port: 443
+  - --no-wait
+",
+        );
+
+        assert_eq!(
+            command.parameters(),
+            vec![
+                "myvm",
+                "--port",
+                "80",
+                "--priority",
+                "900",
+                "--port",
+                "443",
+                "--no-wait"
+            ]
+        );
+    }
+}
@@ -1,14 +1,34 @@
 use super::ArmTemplate;
 use super::CliCommand;
+use super::Tags;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
-#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct Command {
+    /// Identifies the command in messages and deployment names.
+    pub id: Option<String>,
+    /// Commands run from the lowest order to the highest.
     pub order: Option<u32>,
+    /// The subscription for this command, overriding the session and top level subscriptions.
+    pub subscription: Option<String>,
+    /// The resource group to create or deploy to.  Defaults to the rendered resource group template.
     pub resource_group: Option<String>,
+    /// The Azure region for the resource group, overriding the session location.
+    #[schemars(default, schema_with = "super::regions::location_schema")]
     pub location: Option<String>,
+    /// An Azure CLI command to run.  A command has either a cli or a template.
     pub cli: Option<CliCommand>,
+    /// An ARM template to deploy.  A command has either a cli or a template.
     pub template: Option<ArmTemplate>,
+    /// The name of the template deployment.
+    pub deployment_name: Option<String>,
+    /// Previews the template deployment (with what-if) before deploying it.
+    pub what_if: Option<bool>,
+    /// Tags for the resource group, added to the top level tags.
+    pub tags: Option<Tags>,
 }
 
 impl Command {
@@ -37,16 +57,165 @@ impl Command {
             u32::max_value()
         }
     }
+
+    pub fn what_if(&self) -> bool {
+        self.what_if.unwrap_or(false)
+    }
+
+    pub fn tags(&self) -> Tags {
+        self.clone().tags.unwrap_or_default()
+    }
+
+    pub fn deployment_name(&self) -> String {
+        let command = self.clone();
+        if let Some(n) = command.deployment_name {
+            n
+        } else {
+            panic!("Commands must have a deployment name before they are deployed.")
+        }
+    }
+
+    // The key identifying this command within its session: the explicit id if there is one,
+    // then the order, and finally the position of the command in the session.  Orders and
+    // positions have different prefixes, so `order: 2` and the second command differ.
+    pub fn key(&self, position: usize) -> String {
+        if let Some(ref id) = self.id {
+            id.to_string()
+        } else if let Some(o) = self.order {
+            format!("o{}", o)
+        } else {
+            format!("p{}", position)
+        }
+    }
+}
+
+// ARM deployment names are limited to 64 alphanumerics, underscores, parentheses,
+// hyphens and periods.
+const MAX_DEPLOYMENT_NAME_LENGTH: usize = 64;
+const DEPLOYMENT_NAME_HASH_LENGTH: usize = 8;
+
+// Names that are too long keep the command key at the end, after a short hash of the whole
+// name, so commands in a session with a long event name still get different deployments.
+pub fn build_deployment_name(session: &str, event: &str, key: &str) -> String {
+    let name = deployment_name_characters(&format!("{}-{}-{}", session, event, key));
+    if name.len() <= MAX_DEPLOYMENT_NAME_LENGTH {
+        return name;
+    }
+
+    let hash = format!("{:x}", Sha256::digest(name.as_bytes()));
+    let suffix: String = format!(
+        "-{}-{}",
+        &hash[..DEPLOYMENT_NAME_HASH_LENGTH],
+        deployment_name_characters(key)
+    )
+    .chars()
+    .take(MAX_DEPLOYMENT_NAME_LENGTH / 2)
+    .collect();
+    let prefix: String = name
+        .chars()
+        .take(MAX_DEPLOYMENT_NAME_LENGTH - suffix.len())
+        .collect();
+    format!("{}{}", prefix.trim_end_matches('-'), suffix)
+}
+
+fn deployment_name_characters(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "_()-.".contains(c) {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
 }
 
 impl Default for Command {
     fn default() -> Self {
         Command {
+            id: None,
             order: None,
+            subscription: None,
             resource_group: None,
             location: None,
             cli: None,
             template: None,
+            deployment_name: None,
+            what_if: None,
+            tags: None,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_prefers_id_over_order() {
+        let command = Command {
+            id: Some("webapp".to_string()),
+            order: Some(2),
+            ..Default::default()
+        };
+
+        assert_eq!(command.key(1), "webapp");
+    }
+
+    #[test]
+    fn key_falls_back_to_order_then_position() {
+        let mut command = Command::default();
+        assert_eq!(command.key(3), "p3");
+
+        command.order = Some(2);
+        assert_eq!(command.key(3), "o2");
+    }
+
+    #[test]
+    fn orders_and_positions_have_different_keys() {
+        let ordered = Command {
+            order: Some(2),
+            ..Default::default()
+        };
+
+        assert_ne!(ordered.key(1), Command::default().key(2));
+    }
+
+    #[test]
+    fn deployment_name_is_stable() {
+        let first = build_deployment_name("SRE10", "stmuraws", "1");
+        let second = build_deployment_name("SRE10", "stmuraws", "1");
+
+        assert_eq!(first, "SRE10-stmuraws-1");
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn deployment_name_replaces_invalid_characters() {
+        let name = build_deployment_name("SRE10", "DOMAIN\\some user", "1");
+
+        assert_eq!(name, "SRE10-DOMAIN-some-user-1");
+    }
+
+    #[test]
+    fn deployment_name_is_truncated() {
+        let event = "a".repeat(100);
+        let name = build_deployment_name("SRE10", &event, "1");
+
+        assert_eq!(name.len(), MAX_DEPLOYMENT_NAME_LENGTH);
+    }
+
+    #[test]
+    fn truncated_deployment_names_keep_the_key() {
+        let event = "a-very-long-event-name-for-ignite-the-tour-in-amsterdam-2026";
+        let webapp = build_deployment_name("SRE10", event, "webapp");
+        let database = build_deployment_name("SRE10", event, "database");
+
+        assert_ne!(webapp, database);
+        assert!(webapp.starts_with("SRE10-a-very-long"));
+        assert!(webapp.ends_with("-webapp"));
+        assert!(database.ends_with("-database"));
+        assert!(webapp.len() <= MAX_DEPLOYMENT_NAME_LENGTH);
+        assert!(database.len() <= MAX_DEPLOYMENT_NAME_LENGTH);
+    }
+}
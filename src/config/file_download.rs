@@ -1,8 +1,12 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct FileDownload {
+    /// The name to save the file as, when the url does not end in one.
     pub file_name: Option<String>,
+    /// Where to download the file from.
     pub url: Option<String>,
 }
 
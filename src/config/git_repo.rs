@@ -0,0 +1,82 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GitRepo {
+    /// The repository to clone.
+    pub url: Option<String>,
+    /// The branch to check out.  Defaults to the repository's default branch.
+    pub branch: Option<String>,
+}
+
+// Version 1 configurations list repositories as plain urls, which are still accepted.
+#[derive(Deserialize)]
+#[serde(untagged, deny_unknown_fields)]
+enum GitRepoFormat {
+    Url(String),
+    Repo {
+        url: Option<String>,
+        branch: Option<String>,
+    },
+}
+
+impl<'de> Deserialize<'de> for GitRepo {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(match GitRepoFormat::deserialize(deserializer)? {
+            GitRepoFormat::Url(url) => GitRepo::new(&url),
+            GitRepoFormat::Repo { url, branch } => GitRepo { url, branch },
+        })
+    }
+}
+
+impl GitRepo {
+    pub fn new(url: &str) -> Self {
+        GitRepo {
+            url: Some(url.to_string()),
+            branch: None,
+        }
+    }
+
+    pub fn url(&self) -> String {
+        let repo = self.clone();
+        if let Some(u) = repo.url {
+            u
+        } else {
+            panic!("Git repositories must have a url.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repos_can_be_urls_or_maps() {
+        let repos: Vec<GitRepo> = serde_yaml::from_str(
+            "
+- https://github.com/microsoft/ignite-learning-paths-training-sre
+- url: https://github.com/microsoft/ignite-learning-paths-training-ops
+  branch: tour
+",
+        )
+        .unwrap();
+
+        assert_eq!(
+            repos[0],
+            GitRepo::new("https://github.com/microsoft/ignite-learning-paths-training-sre")
+        );
+        assert_eq!(repos[1].branch, Some("tour".to_string()));
+    }
+
+    #[test]
+    fn repos_with_unknown_fields_are_errors() {
+        let repo: Result<GitRepo, _> = serde_yaml::from_str("uri: https://github.com/microsoft");
+
+        assert!(repo.is_err());
+    }
+}
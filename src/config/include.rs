@@ -0,0 +1,394 @@
+use super::migration::upgrade;
+use super::profile::merge_values;
+use super::{load, read_source, ConfigError, Tags, TourConfig, Verification};
+use glob::glob;
+use reqwest::Url;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+// Resolves the includes of a configuration file depth first.  A file included more than once
+// (from different files) is only merged once, a file that includes itself through any chain
+// of includes is an error.
+pub struct IncludeResolver {
+    chain: Vec<String>,
+    included: HashSet<String>,
+    session_origins: HashMap<String, String>,
+    verification: Option<Verification>,
+}
+
+impl IncludeResolver {
+    pub fn new() -> Self {
+        IncludeResolver {
+            chain: Vec::new(),
+            included: HashSet::new(),
+            session_origins: HashMap::new(),
+            verification: None,
+        }
+    }
+
+    // Checks every file, included files too, before it is parsed.
+    pub fn verified(verification: Verification) -> Self {
+        let mut resolver = IncludeResolver::new();
+        resolver.verification = Some(verification);
+        resolver
+    }
+
+    pub fn read(&mut self, path: &str) -> Result<TourConfig, ConfigError> {
+        let content = read_source(path)?;
+        if let Some(ref verification) = self.verification {
+            verification.verify(path, &content, self.chain.is_empty())?;
+        }
+        let tour_config = load(&content, path)?;
+        self.resolve(tour_config, path)
+    }
+
+    fn resolve(
+        &mut self,
+        mut tour_config: TourConfig,
+        path: &str,
+    ) -> Result<TourConfig, ConfigError> {
+        if !self.chain.is_empty() {
+            rebase_templates(&mut tour_config, path)?;
+        }
+        let mut tour_config = upgrade(tour_config, path)?;
+        let key = source_key(path);
+        self.chain.push(key.clone());
+        self.included.insert(key);
+        for session in tour_config.sessions() {
+            self.add_session_origin(session.name, path)?;
+        }
+
+        for include in tour_config.include.take().unwrap_or_default() {
+            for included_path in expand(path, &include)? {
+                let included_key = source_key(&included_path);
+                if self.chain.contains(&included_key) {
+                    let mut chain = self.chain.clone();
+                    chain.push(included_key);
+                    return Err(ConfigError::CyclicInclude {
+                        chain: chain.join(" -> "),
+                    });
+                }
+                if self.included.contains(&included_key) {
+                    continue;
+                }
+
+                let included = self.read(&included_path)?;
+                merge(&mut tour_config, included, &included_path)?;
+            }
+        }
+
+        self.chain.pop();
+        Ok(tour_config)
+    }
+
+    fn add_session_origin(&mut self, name: Option<String>, path: &str) -> Result<(), ConfigError> {
+        let name = match name {
+            Some(n) => n,
+            None => return Ok(()),
+        };
+        if let Some(origin) = self.session_origins.get(&name) {
+            return Err(ConfigError::IncludeConflict {
+                path: path.to_string(),
+                message: format!("the session {} is also defined in {}", name, origin),
+            });
+        }
+        self.session_origins.insert(name, path.to_string());
+        Ok(())
+    }
+}
+
+// Local files are compared by their canonical path, so `./a.yml` and `a.yml` are the same file.
+fn source_key(path: &str) -> String {
+    if Url::parse(path).is_ok() {
+        return path.to_string();
+    }
+    match fs::canonicalize(path) {
+        Ok(p) => p.to_string_lossy().to_string(),
+        Err(_) => path.to_string(),
+    }
+}
+
+fn is_glob(include: &str) -> bool {
+    include.contains(&['*', '?', '['][..])
+}
+
+// Includes are relative to the file that includes them.  Globs are only expanded for local
+// files and match in alphabetical order; a glob that matches nothing is not an error.
+fn expand(path: &str, include: &str) -> Result<Vec<String>, ConfigError> {
+    let invalid = |message: String| ConfigError::InvalidInclude {
+        path: path.to_string(),
+        include: include.to_string(),
+        message,
+    };
+
+    if Url::parse(include).is_ok() {
+        return Ok(vec![include.to_string()]);
+    }
+    if let Ok(base) = Url::parse(path) {
+        if is_glob(include) {
+            return Err(invalid(
+                "globs can only be used in local configuration files".to_string(),
+            ));
+        }
+        return match base.join(include) {
+            Ok(url) => Ok(vec![url.to_string()]),
+            Err(e) => Err(invalid(e.to_string())),
+        };
+    }
+
+    let directory = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+    let included = directory.join(include).to_string_lossy().to_string();
+    if !is_glob(include) {
+        return Ok(vec![included]);
+    }
+
+    let mut paths = Vec::new();
+    for entry in glob(&included).map_err(|e| invalid(e.to_string()))? {
+        let entry = entry.map_err(|e| invalid(e.to_string()))?;
+        if entry.is_file() {
+            paths.push(entry.to_string_lossy().to_string());
+        }
+    }
+    Ok(paths)
+}
+
+// Template paths in an included file are relative to that file, like its includes, so they
+// are rebased before its sessions are merged into a file that may live somewhere else.
+fn rebase_templates(tour_config: &mut TourConfig, path: &str) -> Result<(), ConfigError> {
+    let sessions = match tour_config.sessions {
+        Some(ref mut s) => s,
+        None => return Ok(()),
+    };
+    for template in sessions
+        .iter_mut()
+        .flat_map(|s| s.commands.iter_mut().flatten())
+        .filter_map(|c| c.template.as_mut())
+    {
+        if let Some(template_path) = template.path.take() {
+            let rebased = rebase(path, &template_path)?;
+            if Url::parse(&rebased).is_ok() {
+                template.url = Some(rebased);
+            } else {
+                template.path = Some(rebased);
+            }
+        }
+        if let Some(parameters_file) = template.parameters_file.take() {
+            template.parameters_file = Some(rebase(path, &parameters_file)?);
+        }
+    }
+    Ok(())
+}
+
+fn rebase(path: &str, relative: &str) -> Result<String, ConfigError> {
+    if Url::parse(relative).is_ok() || Path::new(relative).is_absolute() {
+        return Ok(relative.to_string());
+    }
+    if let Ok(base) = Url::parse(path) {
+        return base.join(relative).map(|url| url.to_string()).map_err(|e| {
+            ConfigError::InvalidInclude {
+                path: path.to_string(),
+                include: relative.to_string(),
+                message: e.to_string(),
+            }
+        });
+    }
+    let directory = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+    Ok(directory.join(relative).to_string_lossy().to_string())
+}
+
+// Sessions are appended.  Settings are taken from an included file when the including file
+// does not set them, and a different value for the same setting is a conflict.
+fn merge(into: &mut TourConfig, included: TourConfig, path: &str) -> Result<(), ConfigError> {
+    let conflict = |message: String| ConfigError::IncludeConflict {
+        path: path.to_string(),
+        message,
+    };
+
+    merge_setting(
+        &mut into.subscription,
+        included.subscription,
+        "subscription",
+    )
+    .map_err(conflict)?;
+    merge_setting(&mut into.location, included.location, "location").map_err(conflict)?;
+    merge_setting(
+        &mut into.resource_group_template,
+        included.resource_group_template,
+        "resource_group_template",
+    )
+    .map_err(conflict)?;
+
+    if let Some(extensions) = included.extensions {
+        let mut merged = into.extensions.take().unwrap_or_default();
+        for extension in extensions {
+            if !merged.contains(&extension) {
+                merged.push(extension);
+            }
+        }
+        into.extensions = Some(merged);
+    }
+
+    if let Some(tags) = included.tags {
+        let mut merged: Tags = into.tags.take().unwrap_or_default();
+        for (k, v) in tags {
+            match merged.get(&k) {
+                Some(existing) if *existing != v => {
+                    return Err(conflict(format!(
+                        "the tag {} is already set to {}",
+                        k, existing
+                    )))
+                }
+                _ => {
+                    merged.insert(k, v);
+                }
+            }
+        }
+        into.tags = Some(merged);
+    }
+
+    // Profiles with the same name are merged, so each file can add to a profile.
+    if let Some(profiles) = included.profiles {
+        let mut merged = into.profiles.take().unwrap_or_default();
+        for (name, overlay) in profiles {
+            match merged.get_mut(&name) {
+                Some(existing) => merge_values(existing, overlay, None),
+                None => {
+                    merged.insert(name, overlay);
+                }
+            }
+        }
+        into.profiles = Some(merged);
+    }
+
+    if let Some(sessions) = included.sessions {
+        let mut merged = into.sessions.take().unwrap_or_default();
+        merged.extend(sessions);
+        into.sessions = Some(merged);
+    }
+    Ok(())
+}
+
+fn merge_setting(
+    into: &mut Option<String>,
+    included: Option<String>,
+    name: &str,
+) -> Result<(), String> {
+    match (into.as_ref(), included) {
+        (Some(existing), Some(ref value)) if existing != value => {
+            Err(format!("the {} is already set to {}", name, existing))
+        }
+        (None, value) => {
+            *into = value;
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::get_config;
+
+    #[test]
+    fn included_sessions_are_merged() {
+        let tour_config = get_config("./test/artifacts/includes/demo.yml").unwrap();
+        let names: Vec<String> = tour_config.sessions().iter().map(|s| s.name()).collect();
+
+        assert_eq!(names, vec!["SRE10", "SRE20", "SRE30", "SRE40"]);
+        assert_eq!(tour_config.include, None);
+        assert_eq!(tour_config.location, Some("westus2".to_string()));
+        assert_eq!(tour_config.extensions(), vec!["webapp", "db-up"]);
+    }
+
+    #[test]
+    fn sessions_defined_twice_are_conflicts() {
+        match get_config("./test/artifacts/includes/conflict.yml") {
+            Err(ConfigError::IncludeConflict { path, message }) => {
+                assert_eq!(path, "./test/artifacts/includes/sessions/sre20.yml");
+                assert_eq!(
+                    message,
+                    "the session SRE20 is also defined in ./test/artifacts/includes/conflict.yml"
+                );
+            }
+            r => panic!("Expected a conflict, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn cyclic_includes_are_errors() {
+        match get_config("./test/artifacts/includes/cycle_a.yml") {
+            Err(ConfigError::CyclicInclude { chain }) => {
+                assert!(chain.ends_with("cycle_a.yml"));
+                assert_eq!(chain.matches(" -> ").count(), 2);
+            }
+            r => panic!("Expected a cycle, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn remote_includes_are_relative_to_the_url() {
+        assert_eq!(
+            expand("https://example.com/tour/demo.yml", "sessions/sre10.yml").unwrap(),
+            vec!["https://example.com/tour/sessions/sre10.yml"]
+        );
+        assert!(expand("https://example.com/tour/demo.yml", "sessions/*.yml").is_err());
+    }
+
+    #[test]
+    fn included_templates_are_relative_to_the_included_file() {
+        let tour_config = get_config("./test/artifacts/includes/demo.yml").unwrap();
+        let session = tour_config
+            .sessions()
+            .into_iter()
+            .find(|s| s.name() == "SRE30")
+            .unwrap();
+        let template = session.commands()[0].template.clone().unwrap();
+
+        assert_eq!(
+            template.path(),
+            "test/artifacts/includes/sessions/../../demo_template.json"
+        );
+        assert!(Path::new(&template.path()).is_file());
+        assert!(Path::new(&template.parameters_file.unwrap()).is_file());
+    }
+
+    #[test]
+    fn remote_templates_are_relative_to_the_url() {
+        assert_eq!(
+            rebase(
+                "https://example.com/tour/sessions/sre30.yml",
+                "../templates/web.json"
+            )
+            .unwrap(),
+            "https://example.com/tour/templates/web.json"
+        );
+        assert_eq!(
+            rebase("https://example.com/tour/demo.yml", "/srv/web.json").unwrap(),
+            "/srv/web.json"
+        );
+        assert_eq!(
+            rebase("sessions/sre30.yml", "https://example.com/web.json").unwrap(),
+            "https://example.com/web.json"
+        );
+    }
+
+    #[test]
+    fn different_settings_are_conflicts() {
+        let mut into = TourConfig {
+            location: Some("westus2".to_string()),
+            ..Default::default()
+        };
+        let mut included = TourConfig {
+            location: Some("eastus".to_string()),
+            subscription: Some("demo".to_string()),
+            ..Default::default()
+        };
+
+        assert!(merge(&mut into, included.clone(), "other.yml").is_err());
+        included.location = None;
+        merge(&mut into, included, "other.yml").unwrap();
+        assert_eq!(into.subscription, Some("demo".to_string()));
+    }
+}
@@ -0,0 +1,178 @@
+use super::{read, read_from_url, ArmTemplate, ConfigError, TourConfig};
+use reqwest::Url;
+use serde_yaml::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+// Version 2 added `version:`, repositories as maps and typed template parameters.
+pub const CONFIG_VERSION: u32 = 2;
+
+// Brings a configuration in an older layout up to the current version, so the rest of `demo`
+// only has to understand one layout.  Plain url repositories are already converted when they
+// are parsed.
+pub fn upgrade(mut tour_config: TourConfig, path: &str) -> Result<TourConfig, ConfigError> {
+    let version = tour_config.version();
+    if version > CONFIG_VERSION {
+        return Err(ConfigError::UnsupportedVersion {
+            path: path.to_string(),
+            version,
+        });
+    }
+
+    if version < 2 {
+        type_template_parameters(&mut tour_config);
+    }
+    tour_config.version = Some(CONFIG_VERSION);
+    Ok(tour_config)
+}
+
+// Version 1 template parameters were strings passed to the Azure CLI as `key=value`, which
+// left their type to the template.  Strings are converted for the parameters the template
+// declares as `int` or `bool`, and kept for everything else (or when the template cannot be
+// read).
+fn type_template_parameters(tour_config: &mut TourConfig) {
+    let sessions = match tour_config.sessions {
+        Some(ref mut s) => s,
+        None => return,
+    };
+    for session in sessions.iter_mut() {
+        for template in session
+            .commands
+            .iter_mut()
+            .flatten()
+            .filter_map(|c| c.template.as_mut())
+        {
+            let types = declared_types(template);
+            for (name, value) in template.parameters.iter_mut().flatten() {
+                let declared = types.get(&name.to_lowercase()).map(|t| t.as_str());
+                if let Some(typed) = typed_value(value, declared) {
+                    *value = typed;
+                }
+            }
+        }
+    }
+}
+
+// The declared types of the template parameters, by lower case name (ARM parameter names are
+// case insensitive).
+fn declared_types(template: &ArmTemplate) -> HashMap<String, String> {
+    let has_candidates = template.parameters.iter().flatten().any(|(_, v)| {
+        typed_value(v, Some("int")).is_some() || typed_value(v, Some("bool")).is_some()
+    });
+    if !has_candidates {
+        return HashMap::new();
+    }
+
+    let content = match (&template.path, &template.url) {
+        (Some(path), _) => read(Path::new(path)).ok(),
+        (None, Some(url)) => Url::parse(url).ok().and_then(|u| read_from_url(u).ok()),
+        (None, None) => None,
+    };
+    let document: serde_json::Value = content
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default();
+    let mut types = HashMap::new();
+    for (name, parameter) in document["parameters"].as_object().into_iter().flatten() {
+        if let Some(t) = parameter["type"].as_str() {
+            types.insert(name.to_lowercase(), t.to_lowercase());
+        }
+    }
+    types
+}
+
+fn typed_value(value: &Value, declared: Option<&str>) -> Option<Value> {
+    let s = value.as_str()?.trim();
+    match declared {
+        Some("int") => s.parse::<i64>().ok().map(Value::from),
+        Some("bool") => match s.to_lowercase().as_str() {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::read_config;
+
+    #[test]
+    fn version_1_configs_are_upgraded() {
+        let tour_config = read_config("./test/artifacts/version_1_config.yml").unwrap();
+        assert_eq!(tour_config.version(), 1);
+
+        let tour_config = upgrade(tour_config, "version_1_config.yml").unwrap();
+        let session = &tour_config.sessions()[0];
+        let parameters = session.commands()[0].template.clone().unwrap().parameters;
+        let parameters = parameters.unwrap();
+
+        assert_eq!(tour_config.version, Some(CONFIG_VERSION));
+        assert_eq!(
+            session.git_repos.clone().unwrap()[0].url(),
+            "https://github.com/microsoft/ignite-learning-paths-training-sre"
+        );
+        assert_eq!(parameters["instances"], Value::from(3));
+        assert_eq!(parameters["alwaysOn"], Value::from(true));
+        assert_eq!(parameters["appVersion"], Value::from("2.0"));
+        assert_eq!(parameters["webAppName"], Value::from("demoup"));
+    }
+
+    #[test]
+    fn current_configs_keep_string_parameters() {
+        let mut tour_config = read_config("./test/artifacts/version_1_config.yml").unwrap();
+        tour_config.version = Some(CONFIG_VERSION);
+
+        let tour_config = upgrade(tour_config, "version_1_config.yml").unwrap();
+        let parameters = tour_config.sessions()[0].commands()[0]
+            .template
+            .clone()
+            .unwrap()
+            .parameters
+            .unwrap();
+
+        assert_eq!(parameters["instances"], Value::from("3"));
+    }
+
+    #[test]
+    fn parameters_stay_strings_without_the_template() {
+        let tour_config: TourConfig = serde_yaml::from_str(
+            "
+sessions:
+  - name: SRE10
+    commands:
+      - template:
+          path: ./test/artifacts/missing_template.json
+          parameters:
+            instances: \"3\"
+",
+        )
+        .unwrap();
+
+        let tour_config = upgrade(tour_config, "demo.yml").unwrap();
+        let parameters = tour_config.sessions()[0].commands()[0]
+            .template
+            .clone()
+            .unwrap()
+            .parameters
+            .unwrap();
+
+        assert_eq!(parameters["instances"], Value::from("3"));
+    }
+
+    #[test]
+    fn newer_versions_are_errors() {
+        let tour_config = TourConfig {
+            version: Some(CONFIG_VERSION + 1),
+            ..Default::default()
+        };
+
+        match upgrade(tour_config, "demo.yml") {
+            Err(ConfigError::UnsupportedVersion { version, .. }) => {
+                assert_eq!(version, CONFIG_VERSION + 1)
+            }
+            r => panic!("Expected an unsupported version, got {:?}", r),
+        }
+    }
+}
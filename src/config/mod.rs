@@ -1,18 +1,35 @@
 pub mod arm_template;
+pub mod cache;
 pub mod cli_command;
 pub mod command;
 pub mod file_download;
+pub mod git_repo;
+pub mod include;
+pub mod migration;
+pub mod naming;
+pub mod profile;
+pub mod regions;
 pub mod session;
+pub mod tags;
 pub mod tour_config;
+pub mod validation;
+pub mod verify;
 
 pub use self::arm_template::ArmTemplate;
 pub use self::cli_command::CliCommand;
 pub use self::command::Command;
 pub use self::file_download::FileDownload;
+pub use self::git_repo::GitRepo;
+pub use self::include::IncludeResolver;
+pub use self::profile::apply_profile;
 pub use self::session::Session;
+pub use self::tags::Tags;
 pub use self::tour_config::TourConfig;
+pub use self::verify::Verification;
 
-use reqwest::{Client, Url};
+use self::cache::{cache_directory, is_offline, read_cached};
+use custom_error::custom_error;
+use reqwest::Url;
 use std::fs::File;
 use std::io;
 use std::io::prelude::*;
@@ -26,39 +43,113 @@ pub fn read(path: &Path) -> Result<String, io::Error> {
     Ok(contents)
 }
 
-fn load(yaml_str: &str) -> TourConfig {
-    match serde_yaml::from_str(&yaml_str) {
-        Ok(s) => s,
-        Err(_e) => TourConfig::default(),
+custom_error! {
+    pub ConfigError
+    NotFound{path: String} = "Failed to locate the configuration file {path}.",
+    ReadFailure{path: String, source: io::Error} = "Failed to read {path}: {source}",
+    ParseFailure{path: String, line: usize, column: usize, message: String} = "{path}:{line}:{column}: {message}",
+    InvalidInclude{path: String, include: String, message: String} = "{path}: Failed to include {include}: {message}",
+    IncludeConflict{path: String, message: String} = "{path}: {message}",
+    CyclicInclude{chain: String} = "The configuration files include each other: {chain}",
+    UnknownProfile{profile: String, profiles: String} = "There is no {profile} profile in the configuration file.  Profiles: {profiles}",
+    ProfileFailure{profile: String, message: String} = "Failed to apply the {profile} profile: {message}",
+    ChecksumMismatch{path: String, expected: String, actual: String} = "The SHA-256 of {path} is {actual}, not {expected}.",
+    InvalidSignature{path: String, message: String} = "The signature of {path} is not valid: {message}",
+    InvalidPublicKey{message: String} = "Failed to read the public key: {message}",
+    Unverified{path: String} = "{path} is not signed, and using it was not confirmed.",
+    UnsupportedVersion{path: String, version: u32} = "{path} is a version {version} configuration file, which needs a newer version of demo.",
+}
+
+// Parses strictly, so typos and unknown fields are reported with their position instead of
+// quietly producing an empty configuration.  An empty document is an empty configuration.
+fn load(yaml_str: &str, path: &str) -> Result<TourConfig, ConfigError> {
+    let parse_failure = |e: serde_yaml::Error| {
+        let (line, column) = match e.location() {
+            Some(l) => (l.line(), l.column()),
+            None => (0, 0),
+        };
+        ConfigError::ParseFailure {
+            path: path.to_string(),
+            line,
+            column,
+            message: without_location(&e.to_string()),
+        }
+    };
+
+    if yaml_str.trim().is_empty() {
+        return Ok(TourConfig::default());
     }
+    let document: serde_yaml::Value = serde_yaml::from_str(yaml_str).map_err(parse_failure)?;
+    if document.is_null() {
+        return Ok(TourConfig::default());
+    }
+    serde_yaml::from_str(yaml_str).map_err(parse_failure)
 }
 
-fn read_from_url(url: Url) -> Result<String, io::Error> {
-    let client = Client::new();
-    let mut resp = client
-        .get(url)
-        .send()
-        .expect("Failed to retrieve the configuration file.");
-    let mut contents = String::new();
-    resp.read_to_string(&mut contents)?;
+fn without_location(message: &str) -> String {
+    match message.find(" at line ") {
+        Some(i) => message[..i].to_string(),
+        None => message.to_string(),
+    }
+}
 
-    Ok(contents)
+// Remote files are read through the cache, so they are still available offline.
+pub fn read_from_url(url: Url) -> Result<String, io::Error> {
+    read_cached(url, &cache_directory(), is_offline())
+}
+
+// Reads a configuration file with its includes merged in, each upgraded to the current version.
+pub fn get_config(path: &str) -> Result<TourConfig, ConfigError> {
+    IncludeResolver::new().read(path)
+}
+
+// Reads a configuration file like get_config, with the profile (if any) applied.
+pub fn get_profile_config(path: &str, profile: Option<&str>) -> Result<TourConfig, ConfigError> {
+    with_profile(get_config(path)?, profile)
+}
+
+// Reads a configuration file like get_profile_config, checking each file before it is used.
+pub fn get_verified_config(
+    path: &str,
+    profile: Option<&str>,
+    verification: &Verification,
+) -> Result<TourConfig, ConfigError> {
+    let tour_config = IncludeResolver::verified(verification.clone()).read(path)?;
+    with_profile(tour_config, profile)
+}
+
+fn with_profile(tour_config: TourConfig, profile: Option<&str>) -> Result<TourConfig, ConfigError> {
+    match profile {
+        Some(p) => apply_profile(tour_config, p),
+        None => Ok(tour_config),
+    }
 }
 
-pub fn get_config(path: &str) -> TourConfig {
+// Reads a single configuration file as it is written.
+pub fn read_config(path: &str) -> Result<TourConfig, ConfigError> {
+    load(&read_source(path)?, path)
+}
+
+fn read_source(path: &str) -> Result<String, ConfigError> {
+    let read_failure = |source| ConfigError::ReadFailure {
+        path: path.to_string(),
+        source,
+    };
     let content = match Url::parse(path) {
-        Ok(url) => read_from_url(url).unwrap(),
+        Ok(url) => read_from_url(url).map_err(read_failure)?,
         Err(_) => {
             let p = Path::new(path);
             if p.exists() {
-                read(&p).unwrap()
+                read(&p).map_err(read_failure)?
             } else {
-                panic!("Failed to locate any valid configuration file.")
+                return Err(ConfigError::NotFound {
+                    path: path.to_string(),
+                });
             }
         }
     };
 
-    load(&content)
+    Ok(content)
 }
 
 // trait for create directory and execute
@@ -68,11 +159,11 @@ mod tests {
     use super::*;
 
     fn load_empty_config() -> TourConfig {
-        get_config(&"./test/artifacts/empty_config.yml")
+        get_config(&"./test/artifacts/empty_config.yml").unwrap()
     }
 
     fn load_single_session_config() -> TourConfig {
-        get_config(&"./test/artifacts/single_session_config.yml")
+        get_config(&"./test/artifacts/single_session_config.yml").unwrap()
     }
 
     fn get_single_session() -> Session {
@@ -87,9 +178,51 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
-    fn no_valid_config_provided_located_panics() {
-        get_config(&"./missing.yml");
+    fn no_valid_config_provided_located_is_an_error() {
+        match get_config(&"./missing.yml") {
+            Err(ConfigError::NotFound { path }) => assert_eq!(path, "./missing.yml"),
+            _ => panic!("Expected the configuration file to be missing."),
+        }
+    }
+
+    #[test]
+    fn empty_documents_are_empty_configs() {
+        assert_eq!(load("", "demo.yml").unwrap(), TourConfig::default());
+        assert_eq!(
+            load("---\n# nothing yet\n", "demo.yml").unwrap(),
+            TourConfig::default()
+        );
+    }
+
+    #[test]
+    fn syntax_errors_report_their_position() {
+        let yaml = "sessions:\n  - name: SRE10\n    slides: [\n";
+
+        match load(yaml, "demo.yml") {
+            Err(ConfigError::ParseFailure { path, line, .. }) => {
+                assert_eq!(path, "demo.yml");
+                assert_eq!(line, 4);
+            }
+            _ => panic!("Expected a parse failure."),
+        }
+    }
+
+    #[test]
+    fn unknown_fields_are_errors() {
+        let yaml = "sessions:\n  - name: SRE10\n    gitrepos:\n      - https://github.com/microsoft/ignite-learning-paths\n";
+
+        match load(yaml, "demo.yml") {
+            Err(ConfigError::ParseFailure {
+                line,
+                column,
+                message,
+                ..
+            }) => {
+                assert!(message.contains("unknown field `gitrepos`"));
+                assert_eq!((line, column), (3, 5));
+            }
+            _ => panic!("Expected a parse failure."),
+        }
     }
 
     #[test]
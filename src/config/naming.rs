@@ -0,0 +1,185 @@
+use super::verify::sha256;
+use super::TourConfig;
+use custom_error::custom_error;
+use std::env;
+
+pub const DEFAULT_RESOURCE_GROUP_TEMPLATE: &str = "{session}-{event}";
+
+// Resource group names are limited to 90 alphanumerics, underscores, parentheses, hyphens and
+// periods, and can not end in a period.
+const MAX_RESOURCE_GROUP_NAME_LENGTH: usize = 90;
+
+custom_error! {
+    pub NamingError
+    InvalidResourceGroupName{name: String, reason: String} = "{name} is not a valid resource group name: {reason}.",
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NameParts {
+    pub session: String,
+    pub event: String,
+    pub user: String,
+    pub date: String,
+}
+
+impl NameParts {
+    // A short, stable hash of the session, event and user.
+    pub fn hash(&self) -> String {
+        let key = format!("{}/{}/{}", self.session, self.event, self.user);
+        sha256(&key)[..6].to_string()
+    }
+}
+
+pub fn render_resource_group_name(template: &str, parts: &NameParts) -> String {
+    let name = template
+        .replace("{session}", &parts.session)
+        .replace("{event}", &parts.event)
+        .replace("{user}", user_name(&parts.user))
+        .replace("{date}", &parts.date)
+        .replace("{hash}", &parts.hash());
+    normalize_name(&name)
+}
+
+// Local user names can be `DOMAIN\user` or `user@domain`, only the user part is used.
+fn user_name(user: &str) -> &str {
+    let user = user.rsplit('\\').next().unwrap_or(user);
+    user.split('@').next().unwrap_or(user).trim()
+}
+
+// Characters that do not belong in a resource group name (like the spaces in a user name) are
+// replaced.  Braces are kept, so a placeholder that is not known still fails validation.
+pub fn normalize_name(name: &str) -> String {
+    name.trim()
+        .chars()
+        .map(|c| {
+            if is_valid_character(c) || c == '{' || c == '}' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>()
+        .trim_matches(|c| c == '-' || c == '.')
+        .to_string()
+}
+
+pub fn local_user() -> String {
+    env::var("USER")
+        .or_else(|_| env::var("USERNAME"))
+        .unwrap_or_default()
+}
+
+fn is_valid_character(c: char) -> bool {
+    c.is_alphanumeric() || "_()-.".contains(c)
+}
+
+pub fn validate_resource_group_name(name: &str) -> Result<(), NamingError> {
+    let invalid = |reason: String| {
+        Err(NamingError::InvalidResourceGroupName {
+            name: name.to_string(),
+            reason,
+        })
+    };
+
+    if name.is_empty() {
+        return invalid("it is empty".to_string());
+    }
+    if name.chars().count() > MAX_RESOURCE_GROUP_NAME_LENGTH {
+        return invalid(format!(
+            "it is longer than {} characters",
+            MAX_RESOURCE_GROUP_NAME_LENGTH
+        ));
+    }
+    if name.ends_with('.') {
+        return invalid("it ends with a period".to_string());
+    }
+    if let Some(c) = name.chars().find(|c| !is_valid_character(*c)) {
+        return invalid(format!("it contains '{}'", c));
+    }
+    Ok(())
+}
+
+pub fn check_resource_group_names(tour_config: &TourConfig) -> Result<(), NamingError> {
+    for session in tour_config.sessions() {
+        for command in session.commands() {
+            validate_resource_group_name(&command.resource_group.unwrap_or_default())?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts() -> NameParts {
+        NameParts {
+            session: "SRE10".to_string(),
+            event: "tour".to_string(),
+            user: "CONTOSO\\Jane Doe".to_string(),
+            date: "20191104".to_string(),
+        }
+    }
+
+    #[test]
+    fn default_template_is_session_and_event() {
+        assert_eq!(
+            render_resource_group_name(DEFAULT_RESOURCE_GROUP_TEMPLATE, &parts()),
+            "SRE10-tour"
+        );
+    }
+
+    #[test]
+    fn all_placeholders_are_replaced() {
+        let name = render_resource_group_name("demo-{user}-{session}-{date}-{hash}", &parts());
+
+        assert!(name.starts_with("demo-Jane-Doe-SRE10-20191104-"));
+        assert_eq!(name.len(), "demo-Jane-Doe-SRE10-20191104-".len() + 6);
+        assert!(validate_resource_group_name(&name).is_ok());
+    }
+
+    #[test]
+    fn hash_is_stable() {
+        let mut other = parts();
+        other.session = "SRE20".to_string();
+
+        assert_eq!(parts().hash(), parts().hash());
+        assert_ne!(parts().hash(), other.hash());
+    }
+
+    #[test]
+    fn user_names_are_normalized() {
+        assert_eq!(user_name("CONTOSO\\jdoe"), "jdoe");
+        assert_eq!(user_name("jdoe@contoso.com"), "jdoe");
+        assert_eq!(normalize_name(" Jane Doe "), "Jane-Doe");
+        assert_eq!(normalize_name("o'brien."), "o-brien");
+    }
+
+    #[test]
+    fn events_are_kept_verbatim() {
+        let mut parts = parts();
+        parts.event = "ams@ignite\\2019".to_string();
+
+        assert_eq!(
+            render_resource_group_name("{session}-{event}", &parts),
+            "SRE10-ams-ignite-2019"
+        );
+    }
+
+    #[test]
+    fn unknown_placeholders_stay_invalid() {
+        let name = render_resource_group_name("{session}-{unknown}", &parts());
+
+        assert_eq!(name, "SRE10-{unknown}");
+        assert!(validate_resource_group_name(&name).is_err());
+    }
+
+    #[test]
+    fn invalid_names_are_rejected() {
+        assert!(validate_resource_group_name("").is_err());
+        assert!(validate_resource_group_name("SRE10-tour.").is_err());
+        assert!(validate_resource_group_name("SRE10-{unknown}").is_err());
+        assert!(validate_resource_group_name(&"a".repeat(91)).is_err());
+        assert!(validate_resource_group_name(&"a".repeat(90)).is_ok());
+    }
+}
@@ -0,0 +1,142 @@
+use super::{ConfigError, TourConfig};
+use serde_yaml::{Mapping, Value};
+
+// Applies a profile over the configuration.  A profile is written like a configuration file
+// with only the settings that differ, and is merged in as YAML so any setting can be
+// overridden: maps are merged key by key, sessions are matched by name and commands by id
+// (unmatched ones are added), and anything else replaces the base value.
+pub fn apply_profile(tour_config: TourConfig, profile: &str) -> Result<TourConfig, ConfigError> {
+    let mut profiles = tour_config.profiles.clone().unwrap_or_default();
+    let overlay = match profiles.remove(profile) {
+        Some(p) => p,
+        None => {
+            let mut names: Vec<String> = profiles.keys().cloned().collect();
+            if names.is_empty() {
+                names.push("none".to_string());
+            }
+            return Err(ConfigError::UnknownProfile {
+                profile: profile.to_string(),
+                profiles: names.join(", "),
+            });
+        }
+    };
+
+    let failure = |e: serde_yaml::Error| ConfigError::ProfileFailure {
+        profile: profile.to_string(),
+        message: e.to_string(),
+    };
+    let mut base = tour_config;
+    base.profiles = None;
+    let mut document = serde_yaml::to_value(&base).map_err(failure)?;
+    merge_values(&mut document, overlay, None);
+
+    let mut tour_config: TourConfig = serde_yaml::from_value(document).map_err(failure)?;
+    tour_config.profiles = None;
+    Ok(tour_config)
+}
+
+// The field that identifies the items of a list, so they can be merged item by item.
+fn identity_field(key: &str) -> Option<&'static str> {
+    match key {
+        "sessions" => Some("name"),
+        "commands" => Some("id"),
+        _ => None,
+    }
+}
+
+pub fn merge_values(base: &mut Value, overlay: Value, key: Option<&str>) {
+    match (base, overlay) {
+        (Value::Mapping(base), Value::Mapping(overlay)) => merge_mappings(base, overlay),
+        (Value::Sequence(base), Value::Sequence(overlay)) => match key.and_then(identity_field) {
+            Some(field) => merge_sequences(base, overlay, field),
+            None => *base = overlay,
+        },
+        (base, overlay) => *base = overlay,
+    }
+}
+
+fn merge_mappings(base: &mut Mapping, overlay: Mapping) {
+    for (k, v) in overlay {
+        let key = k.as_str().map(|s| s.to_string());
+        match base.get_mut(&k) {
+            Some(existing) => merge_values(existing, v, key.as_deref()),
+            None => {
+                base.insert(k, v);
+            }
+        }
+    }
+}
+
+fn merge_sequences(base: &mut Vec<Value>, overlay: Vec<Value>, field: &str) {
+    let field = Value::from(field);
+    for item in overlay {
+        let existing = item
+            .get(&field)
+            .filter(|id| !id.is_null())
+            .and_then(|id| base.iter_mut().find(|b| b.get(&field) == Some(id)));
+        match existing {
+            Some(b) => merge_values(b, item, None),
+            None => base.push(item),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::get_config;
+
+    fn rehearsal() -> TourConfig {
+        let tour_config = get_config("./test/artifacts/profiles_config.yml").unwrap();
+        apply_profile(tour_config, "rehearsal").unwrap()
+    }
+
+    #[test]
+    fn profiles_override_settings() {
+        let tour_config = rehearsal();
+
+        assert_eq!(tour_config.location, Some("eastus".to_string()));
+        assert_eq!(tour_config.subscription, Some("demo".to_string()));
+        assert_eq!(tour_config.profiles, None);
+    }
+
+    #[test]
+    fn profiles_merge_sessions_by_name_and_commands_by_id() {
+        let sessions = rehearsal().sessions();
+        let names: Vec<String> = sessions.iter().map(|s| s.name()).collect();
+        let commands = sessions[0].commands();
+        let parameters = commands[0].template.clone().unwrap().parameters.unwrap();
+
+        assert_eq!(names, vec!["SRE10", "SRE20", "SRE30"]);
+        assert_eq!(commands.len(), 2);
+        assert_eq!(parameters["sku"], Value::from("F1"));
+        assert_eq!(parameters["webAppName"], Value::from("demoup"));
+        assert_eq!(commands[1].cli().subcommand(), vec!["webapp", "list"]);
+        assert_eq!(sessions[1].location, Some("westus3".to_string()));
+    }
+
+    #[test]
+    fn unknown_profiles_are_errors() {
+        let tour_config = get_config("./test/artifacts/profiles_config.yml").unwrap();
+
+        match apply_profile(tour_config, "production") {
+            Err(ConfigError::UnknownProfile { profiles, .. }) => {
+                assert_eq!(profiles, "rehearsal, stage")
+            }
+            r => panic!("Expected an unknown profile, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn lists_without_an_identity_are_replaced() {
+        let mut base: Value = serde_yaml::from_str("extensions: [webapp]\ntags: {a: b}").unwrap();
+        let overlay: Value = serde_yaml::from_str("extensions: [db-up]\ntags: {c: d}").unwrap();
+
+        merge_values(&mut base, overlay, None);
+
+        assert_eq!(
+            base,
+            serde_yaml::from_str::<Value>("extensions: [db-up]\ntags: {a: b, c: d}").unwrap()
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use super::TourConfig;
+use custom_error::custom_error;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Metadata, Schema, SchemaObject};
+
+custom_error! {
+    pub LocationError
+    MissingLocation{resource_group: String} = "No location for the {resource_group} resource group.  Use --location or set a location in the configuration file.",
+    UnknownLocation{location: String} = "{location} is not a known Azure region.  Use --allow-unknown-location for regions newer than this release.",
+}
+
+// Azure regions by their Azure Resource Manager names.  New regions open all the time, so this is
+// only used to catch typos.
+const AZURE_REGIONS: &[&str] = &[
+    "australiacentral",
+    "australiacentral2",
+    "australiaeast",
+    "australiasoutheast",
+    "brazilsouth",
+    "brazilsoutheast",
+    "canadacentral",
+    "canadaeast",
+    "centralindia",
+    "centralus",
+    "chinaeast",
+    "chinaeast2",
+    "chinanorth",
+    "chinanorth2",
+    "eastasia",
+    "eastus",
+    "eastus2",
+    "francecentral",
+    "francesouth",
+    "germanynorth",
+    "germanywestcentral",
+    "israelcentral",
+    "italynorth",
+    "japaneast",
+    "japanwest",
+    "koreacentral",
+    "koreasouth",
+    "mexicocentral",
+    "newzealandnorth",
+    "northcentralus",
+    "northeurope",
+    "norwayeast",
+    "norwaywest",
+    "polandcentral",
+    "qatarcentral",
+    "southafricanorth",
+    "southafricawest",
+    "southcentralus",
+    "southeastasia",
+    "southindia",
+    "spaincentral",
+    "swedencentral",
+    "switzerlandnorth",
+    "switzerlandwest",
+    "uaecentral",
+    "uaenorth",
+    "uksouth",
+    "ukwest",
+    "usdodcentral",
+    "usdodeast",
+    "usgovarizona",
+    "usgovtexas",
+    "usgovvirginia",
+    "westcentralus",
+    "westeurope",
+    "westindia",
+    "westus",
+    "westus2",
+    "westus3",
+];
+
+// Accepts display names too, so "West US 2" is treated as "westus2".
+pub fn normalize_location(location: &str) -> String {
+    location
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+pub fn is_azure_region(location: &str) -> bool {
+    AZURE_REGIONS.contains(&normalize_location(location).as_str())
+}
+
+// Any string is allowed since display names are accepted, with the region names as examples
+// for editors to suggest.
+pub fn location_schema(_: &mut SchemaGenerator) -> Schema {
+    let schema = SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        metadata: Some(Box::new(Metadata {
+            examples: AZURE_REGIONS.iter().map(|r| (*r).into()).collect(),
+            ..Default::default()
+        })),
+        ..Default::default()
+    };
+    schema.into()
+}
+
+// Every command needs a known region for its resource group, which is checked before anything
+// is provisioned.  When unknown regions are allowed, they are returned to be reported and left
+// to Azure to accept or reject.
+pub fn check_locations(
+    tour_config: &TourConfig,
+    allow_unknown: bool,
+) -> Result<Vec<String>, LocationError> {
+    let mut unknown = Vec::new();
+    for session in tour_config.sessions() {
+        for command in session.commands() {
+            match command.location {
+                Some(ref l) if is_azure_region(l) => {}
+                Some(l) if !allow_unknown => {
+                    return Err(LocationError::UnknownLocation { location: l })
+                }
+                Some(l) => {
+                    if !unknown.contains(&l) {
+                        unknown.push(l);
+                    }
+                }
+                None => {
+                    return Err(LocationError::MissingLocation {
+                        resource_group: command.resource_group.unwrap_or_default(),
+                    })
+                }
+            }
+        }
+    }
+    Ok(unknown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Command, Session};
+
+    fn config_with_location(location: Option<&str>) -> TourConfig {
+        let command = Command {
+            resource_group: Some("SRE10-tour".to_string()),
+            location: location.map(|l| l.to_string()),
+            ..Default::default()
+        };
+        let session = Session {
+            name: Some("SRE10".to_string()),
+            commands: Some(vec![command]),
+            ..Default::default()
+        };
+        TourConfig {
+            sessions: Some(vec![session]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn display_names_are_regions() {
+        assert!(is_azure_region("westus2"));
+        assert!(is_azure_region("West US 2"));
+        assert!(!is_azure_region("west-us-2"));
+    }
+
+    #[test]
+    fn known_locations_are_valid() {
+        let unknown = check_locations(&config_with_location(Some("eastus")), false).unwrap();
+
+        assert!(unknown.is_empty());
+    }
+
+    #[test]
+    fn unknown_locations_are_rejected() {
+        match check_locations(&config_with_location(Some("westus22")), false) {
+            Err(LocationError::UnknownLocation { location }) => assert_eq!(location, "westus22"),
+            _ => panic!("Expected an unknown location."),
+        }
+    }
+
+    #[test]
+    fn allowed_unknown_locations_are_reported() {
+        let unknown = check_locations(&config_with_location(Some("moon")), true).unwrap();
+
+        assert_eq!(unknown, vec!["moon"]);
+    }
+
+    #[test]
+    fn missing_locations_are_rejected() {
+        match check_locations(&config_with_location(None), true) {
+            Err(LocationError::MissingLocation { resource_group }) => {
+                assert_eq!(resource_group, "SRE10-tour")
+            }
+            _ => panic!("Expected a missing location."),
+        }
+    }
+}
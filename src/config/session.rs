@@ -1,13 +1,28 @@
 use super::Command;
 use super::FileDownload;
+use super::GitRepo;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct Session {
+    /// The session code, such as SRE10.  Also the name of the session's directory.
     pub name: Option<String>,
+    /// The subscription for the session's commands, overriding the top level subscription.
+    pub subscription: Option<String>,
+    /// The Azure region for the session's resource groups.
+    #[schemars(default, schema_with = "super::regions::location_schema")]
+    pub location: Option<String>,
+    /// The template for the session's resource group names, overriding the top level template.
+    pub resource_group_template: Option<String>,
+    /// The slide deck, downloaded to the slides directory.
     pub slides: Option<FileDownload>,
+    /// Demo videos, downloaded to the videos directory.
     pub videos: Option<Vec<FileDownload>>,
-    pub git_repos: Option<Vec<String>>,
+    /// Git repositories, cloned to the src directory.
+    pub git_repos: Option<Vec<GitRepo>>,
+    /// Azure CLI commands and ARM template deployments, run in order.
     pub commands: Option<Vec<Command>>,
 }
 
@@ -35,6 +50,9 @@ impl Default for Session {
     fn default() -> Self {
         Session {
             name: None,
+            subscription: None,
+            location: None,
+            resource_group_template: None,
             slides: None,
             git_repos: None,
             videos: None,
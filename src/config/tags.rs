@@ -0,0 +1,141 @@
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use std::collections::BTreeMap;
+
+// Tags the tool puts on every resource group it creates, so spend can be attributed and
+// forgotten environments can be found (and cleaned up) later.
+pub const EVENT_TAG: &str = "demo-up-event";
+pub const SESSION_TAG: &str = "demo-up-session";
+pub const PRESENTER_TAG: &str = "demo-up-presenter";
+pub const VERSION_TAG: &str = "demo-up-version";
+pub const CREATED_TAG: &str = "demo-up-created";
+pub const EXPIRES_TAG: &str = "demo-up-expires";
+
+pub type Tags = BTreeMap<String, String>;
+
+pub fn tool_tags(
+    event: &str,
+    session: &str,
+    presenter: Option<&str>,
+    created: DateTime<Utc>,
+    expires_in: Duration,
+) -> Tags {
+    let mut tags = Tags::new();
+    tags.insert(EVENT_TAG.to_string(), event.to_string());
+    tags.insert(SESSION_TAG.to_string(), session.to_string());
+    if let Some(p) = presenter {
+        tags.insert(PRESENTER_TAG.to_string(), p.to_string());
+    }
+    tags.insert(
+        VERSION_TAG.to_string(),
+        env!("CARGO_PKG_VERSION").to_string(),
+    );
+    tags.insert(CREATED_TAG.to_string(), format_timestamp(created));
+    tags.insert(
+        EXPIRES_TAG.to_string(),
+        format_timestamp(created + expires_in),
+    );
+    tags
+}
+
+// Configuration tags, then command tags, with the tool's own tags taking precedence.
+pub fn merge_tags(
+    config_tags: Option<&Tags>,
+    command_tags: Option<&Tags>,
+    tool_tags: Tags,
+) -> Tags {
+    let mut tags = Tags::new();
+    if let Some(t) = config_tags {
+        tags.extend(t.clone());
+    }
+    if let Some(t) = command_tags {
+        tags.extend(t.clone());
+    }
+    tags.extend(tool_tags);
+    tags
+}
+
+// Tool tags change with every run, so they are not written to configuration files.
+pub fn without_tool_tags(tags: &Tags) -> Tags {
+    let tool_tags = [
+        EVENT_TAG,
+        SESSION_TAG,
+        PRESENTER_TAG,
+        VERSION_TAG,
+        CREATED_TAG,
+        EXPIRES_TAG,
+    ];
+    tags.iter()
+        .filter(|(k, _)| !tool_tags.contains(&k.as_str()))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+pub fn format_timestamp(timestamp: DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+pub fn parse_timestamp(tags: &Tags, key: &str) -> Option<DateTime<Utc>> {
+    tags.get(key)
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| t.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn created() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2019, 11, 4, 9, 30, 0).unwrap()
+    }
+
+    #[test]
+    fn tool_tags_include_the_expiry() {
+        let tags = tool_tags(
+            "tour",
+            "SRE10",
+            Some("steven"),
+            created(),
+            Duration::hours(72),
+        );
+
+        assert_eq!(tags[EVENT_TAG], "tour");
+        assert_eq!(tags[SESSION_TAG], "SRE10");
+        assert_eq!(tags[PRESENTER_TAG], "steven");
+        assert_eq!(tags[CREATED_TAG], "2019-11-04T09:30:00Z");
+        assert_eq!(tags[EXPIRES_TAG], "2019-11-07T09:30:00Z");
+        assert_eq!(
+            parse_timestamp(&tags, EXPIRES_TAG),
+            Utc.with_ymd_and_hms(2019, 11, 7, 9, 30, 0).single()
+        );
+    }
+
+    #[test]
+    fn invalid_timestamps_are_ignored() {
+        let mut tags = Tags::new();
+        tags.insert(EXPIRES_TAG.to_string(), "next tuesday".to_string());
+
+        assert_eq!(parse_timestamp(&tags, EXPIRES_TAG), None);
+    }
+
+    #[test]
+    fn tool_tags_take_precedence() {
+        let mut config_tags = Tags::new();
+        config_tags.insert("cost-center".to_string(), "tour".to_string());
+        config_tags.insert("owner".to_string(), "events".to_string());
+        let mut command_tags = Tags::new();
+        command_tags.insert("owner".to_string(), "sre".to_string());
+        command_tags.insert(EVENT_TAG.to_string(), "other".to_string());
+
+        let tags = merge_tags(
+            Some(&config_tags),
+            Some(&command_tags),
+            tool_tags("tour", "SRE10", None, created(), Duration::hours(1)),
+        );
+
+        assert_eq!(tags["cost-center"], "tour");
+        assert_eq!(tags["owner"], "sre");
+        assert_eq!(tags[EVENT_TAG], "tour");
+        assert!(!tags.contains_key(PRESENTER_TAG));
+    }
+}
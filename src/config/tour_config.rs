@@ -1,20 +1,56 @@
+use super::command::build_deployment_name;
+use super::naming::{
+    local_user, render_resource_group_name, NameParts, DEFAULT_RESOURCE_GROUP_TEMPLATE,
+};
+use super::regions::normalize_location;
+use super::tags::{merge_tags, tool_tags};
 use super::Command;
 use super::Session;
+use super::Tags;
 use crate::up::UpCommand;
+use chrono::{Duration, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::prelude::*;
 use std::path::PathBuf;
 
-#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct TourConfig {
+    /// The version of the configuration format.  Configurations without a version are version 1.
+    pub version: Option<u32>,
+    /// Other configuration files whose sessions are merged into this one.  Local paths (relative
+    /// to this file, and may be globs) or URLs.
+    pub include: Option<Vec<String>>,
+    /// The default subscription for all sessions.
     pub subscription: Option<String>,
+    /// The default Azure region for all sessions.
+    #[schemars(default, schema_with = "super::regions::location_schema")]
+    pub location: Option<String>,
+    /// The template for resource group names, using {session}, {event}, {user}, {date} and {hash}.
+    pub resource_group_template: Option<String>,
+    /// Azure CLI extensions the commands need.  They are not installed, `demo doctor` checks for
+    /// them.
+    pub extensions: Option<Vec<String>>,
+    /// Tags for every resource group.
+    pub tags: Option<Tags>,
+    /// The sessions presented at the event.
     pub sessions: Option<Vec<Session>>,
+    /// Named overrides, selected with --profile.  A profile is written like this file with only
+    /// the settings that differ; sessions are matched by name and commands by id.
+    #[schemars(with = "Option<BTreeMap<String, serde_json::Value>>")]
+    pub profiles: Option<BTreeMap<String, serde_yaml::Value>>,
 }
 
 impl TourConfig {
     pub fn write(&self, file_path: &PathBuf) {
-        if let Ok(content) = serde_yaml::to_string(self) {
+        let mut document =
+            serde_yaml::to_value(self).expect("Failed to serialize the configuration.");
+        without_unset(&mut document);
+        if let Ok(content) = serde_yaml::to_string(&document) {
             let mut file = File::create(file_path).expect("Failed to create the output file.");
             file.write_all(content.into_bytes().as_ref()).unwrap();
         } else {
@@ -32,19 +68,62 @@ impl TourConfig {
         if !cli_args.subscription.is_empty() {
             self.subscription = Some(cli_args.subscription.clone());
         }
+        let created = Utc::now();
+        let expires_in = Duration::hours(i64::from(cli_args.expires_in));
         let mut updated_sessions: Vec<Session> = Vec::new();
         for session in self.filter_sessions(&cli_args.session_names()) {
             let mut updated_session = session.clone();
+            let template = session
+                .resource_group_template
+                .clone()
+                .or_else(|| self.resource_group_template.clone())
+                .unwrap_or_else(|| DEFAULT_RESOURCE_GROUP_TEMPLATE.to_string());
+            let name_parts = NameParts {
+                session: session.name(),
+                event: cli_args.event(),
+                user: local_user(),
+                date: created.format("%Y%m%d").to_string(),
+            };
             let mut updated_commands: Vec<Command> = Vec::new();
-            for command in session.commands() {
+            for (index, command) in session.commands().iter().enumerate() {
                 let mut updated_command = command.clone();
                 if updated_command.resource_group.is_none() {
-                    let resource_group_name = format!("{}-{}", session.name(), cli_args.event());
+                    let resource_group_name = render_resource_group_name(&template, &name_parts);
                     updated_command.resource_group = Some(resource_group_name);
                 }
+                if updated_command.subscription.is_none() {
+                    updated_command.subscription = session
+                        .subscription
+                        .clone()
+                        .or_else(|| self.subscription.clone());
+                }
+                // The most specific location wins: the command, the session, the command line
+                // and finally the configuration file.
                 if updated_command.location.is_none() {
-                    updated_command.location = cli_args.location.clone();
+                    updated_command.location = session
+                        .location
+                        .clone()
+                        .or_else(|| cli_args.location.clone())
+                        .or_else(|| self.location.clone());
+                }
+                updated_command.location = updated_command.location.map(|l| normalize_location(&l));
+                if updated_command.deployment_name.is_none() {
+                    let deployment_name = build_deployment_name(
+                        &session.name(),
+                        &cli_args.event(),
+                        &command.key(index + 1),
+                    );
+                    updated_command.deployment_name = Some(deployment_name);
                 }
+                let tags = tool_tags(
+                    &cli_args.event(),
+                    &session.name(),
+                    cli_args.presenter.as_deref(),
+                    created,
+                    expires_in,
+                );
+                updated_command.tags =
+                    Some(merge_tags(self.tags.as_ref(), command.tags.as_ref(), tags));
                 updated_commands.push(updated_command);
             }
             updated_session.commands = Some(updated_commands);
@@ -54,13 +133,12 @@ impl TourConfig {
         self
     }
 
-    pub fn subscription(&self) -> String {
-        let config = self.clone();
-        if let Some(s) = config.subscription {
-            s
-        } else {
-            panic!("A subscription needs to be defined, either in the configuration file or on the command line.")
-        }
+    pub fn extensions(&self) -> Vec<String> {
+        self.clone().extensions.unwrap_or_default()
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version.unwrap_or(1)
     }
 
     pub fn sessions(&self) -> Vec<Session> {
@@ -73,11 +151,42 @@ impl TourConfig {
     }
 }
 
+// Settings that are not set are left out of written files.  Nulls inside parameters and
+// profiles are kept, since they mean something there (like a CLI switch).
+fn without_unset(value: &mut Value) {
+    match value {
+        Value::Mapping(mapping) => {
+            let unset: Vec<Value> = mapping
+                .iter()
+                .filter(|(_, v)| v.is_null())
+                .map(|(k, _)| k.clone())
+                .collect();
+            for key in unset {
+                mapping.remove(&key);
+            }
+            for (key, v) in mapping.iter_mut() {
+                if !matches!(key.as_str(), Some("parameters") | Some("profiles")) {
+                    without_unset(v);
+                }
+            }
+        }
+        Value::Sequence(sequence) => sequence.iter_mut().for_each(without_unset),
+        _ => {}
+    }
+}
+
 impl Default for TourConfig {
     fn default() -> Self {
         TourConfig {
+            version: None,
+            include: None,
             subscription: None,
+            location: None,
+            resource_group_template: None,
+            extensions: None,
+            tags: None,
             sessions: None,
+            profiles: None,
         }
     }
 }
@@ -88,23 +197,270 @@ mod tests {
 
     #[test]
     fn session_filter_returns_correct_sessions() {
-        let mut session_a = Session::default();
-        session_a.name = Some("SRE10".to_string());
-        let mut session_b = Session::default();
-        session_b.name = Some("SRE20".to_string());
-        let mut session_c = Session::default();
-        session_c.name = Some("SRE30".to_string());
-
-        let mut tour_config = TourConfig::default();
-        tour_config.sessions = Some(vec![session_a, session_b, session_c]);
+        let session = |name: &str| Session {
+            name: Some(name.to_string()),
+            ..Default::default()
+        };
+        let tour_config = TourConfig {
+            sessions: Some(vec![session("SRE10"), session("SRE20"), session("SRE30")]),
+            ..Default::default()
+        };
 
         let filter_list = vec!["SRE20".to_string()];
 
         let result = tour_config.filter_sessions(&filter_list);
-        let mut session_d = Session::default();
-        session_d.name = Some("SRE20".to_string());
-        let expected = vec![session_d];
+        let expected = vec![session("SRE20")];
 
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn update_prefers_the_most_specific_location() {
+        let located = Command {
+            location: Some("eastus".to_string()),
+            ..Default::default()
+        };
+        let session_a = Session {
+            name: Some("SRE10".to_string()),
+            location: Some("West Europe".to_string()),
+            commands: Some(vec![located, Command::default()]),
+            ..Default::default()
+        };
+        let session_b = Session {
+            name: Some("SRE20".to_string()),
+            commands: Some(vec![Command::default()]),
+            ..Default::default()
+        };
+
+        let mut tour_config = TourConfig {
+            location: Some("centralus".to_string()),
+            sessions: Some(vec![session_a, session_b]),
+            ..Default::default()
+        };
+
+        let cli_args = UpCommand {
+            event: Some("tour".to_string()),
+            location: Some("westus2".to_string()),
+            session_names: Some(vec!["SRE10".to_string(), "SRE20".to_string()]),
+            ..Default::default()
+        };
+
+        tour_config.update(&cli_args);
+        let locations: Vec<Option<String>> = tour_config
+            .sessions()
+            .iter()
+            .flat_map(|s| s.commands())
+            .map(|c| c.location)
+            .collect();
+
+        assert_eq!(
+            locations,
+            vec![
+                Some("eastus".to_string()),
+                Some("westeurope".to_string()),
+                Some("westus2".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn update_uses_the_session_resource_group_template() {
+        let session_a = Session {
+            name: Some("SRE10".to_string()),
+            resource_group_template: Some("{event}-{session}-gpu".to_string()),
+            commands: Some(vec![Command::default()]),
+            ..Default::default()
+        };
+        let session_b = Session {
+            name: Some("SRE20".to_string()),
+            commands: Some(vec![Command::default()]),
+            ..Default::default()
+        };
+
+        let mut tour_config = TourConfig {
+            resource_group_template: Some("demo-{session}-{event}".to_string()),
+            sessions: Some(vec![session_a, session_b]),
+            ..Default::default()
+        };
+
+        let cli_args = UpCommand {
+            event: Some("DOMAIN\\some user".to_string()),
+            session_names: Some(vec!["SRE10".to_string(), "SRE20".to_string()]),
+            ..Default::default()
+        };
+
+        tour_config.update(&cli_args);
+        let names: Vec<Option<String>> = tour_config
+            .sessions()
+            .iter()
+            .flat_map(|s| s.commands())
+            .map(|c| c.resource_group)
+            .collect();
+
+        assert_eq!(
+            names,
+            vec![
+                Some("DOMAIN-some-user-SRE10-gpu".to_string()),
+                Some("demo-SRE20-DOMAIN-some-user".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn update_prefers_the_session_subscription() {
+        let gpu = Command {
+            subscription: Some("GPU".to_string()),
+            ..Default::default()
+        };
+        let session_a = Session {
+            name: Some("SRE10".to_string()),
+            subscription: Some("Preview".to_string()),
+            commands: Some(vec![Command::default(), gpu]),
+            ..Default::default()
+        };
+        let session_b = Session {
+            name: Some("SRE20".to_string()),
+            commands: Some(vec![Command::default()]),
+            ..Default::default()
+        };
+
+        let mut tour_config = TourConfig {
+            subscription: Some("Ignite the Tour".to_string()),
+            sessions: Some(vec![session_a, session_b]),
+            ..Default::default()
+        };
+
+        let cli_args = UpCommand {
+            event: Some("tour".to_string()),
+            subscription: "Rehearsal".to_string(),
+            session_names: Some(vec!["SRE10".to_string(), "SRE20".to_string()]),
+            ..Default::default()
+        };
+
+        tour_config.update(&cli_args);
+        let subscriptions: Vec<Option<String>> = tour_config
+            .sessions()
+            .iter()
+            .flat_map(|s| s.commands())
+            .map(|c| c.subscription)
+            .collect();
+
+        assert_eq!(
+            subscriptions,
+            vec![
+                Some("Preview".to_string()),
+                Some("GPU".to_string()),
+                Some("Rehearsal".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn update_tags_commands() {
+        let mut command_tags = Tags::new();
+        command_tags.insert("owner".to_string(), "sre".to_string());
+        let tagged = Command {
+            tags: Some(command_tags),
+            ..Default::default()
+        };
+
+        let session = Session {
+            name: Some("SRE10".to_string()),
+            commands: Some(vec![tagged]),
+            ..Default::default()
+        };
+
+        let mut config_tags = Tags::new();
+        config_tags.insert("cost-center".to_string(), "tour".to_string());
+        let mut tour_config = TourConfig {
+            tags: Some(config_tags),
+            sessions: Some(vec![session]),
+            ..Default::default()
+        };
+
+        let cli_args = UpCommand {
+            event: Some("tour".to_string()),
+            presenter: Some("steven".to_string()),
+            session_names: Some(vec!["SRE10".to_string()]),
+            ..Default::default()
+        };
+
+        tour_config.update(&cli_args);
+        let tags = tour_config.sessions()[0].commands()[0].tags();
+
+        assert_eq!(tags["cost-center"], "tour");
+        assert_eq!(tags["owner"], "sre");
+        assert_eq!(tags["demo-up-event"], "tour");
+        assert_eq!(tags["demo-up-session"], "SRE10");
+        assert_eq!(tags["demo-up-presenter"], "steven");
+        assert!(tags.contains_key("demo-up-expires"));
+    }
+
+    #[test]
+    fn update_sets_deployment_names() {
+        let ordered = Command {
+            order: Some(2),
+            ..Default::default()
+        };
+        let named = Command {
+            id: Some("webapp".to_string()),
+            ..Default::default()
+        };
+        let deployment_named = Command {
+            deployment_name: Some("custom".to_string()),
+            ..Default::default()
+        };
+
+        let session = Session {
+            name: Some("SRE10".to_string()),
+            commands: Some(vec![ordered, Command::default(), named, deployment_named]),
+            ..Default::default()
+        };
+
+        let mut tour_config = TourConfig {
+            sessions: Some(vec![session]),
+            ..Default::default()
+        };
+
+        let cli_args = UpCommand {
+            event: Some("tour".to_string()),
+            session_names: Some(vec!["SRE10".to_string()]),
+            ..Default::default()
+        };
+
+        tour_config.update(&cli_args);
+        let names: Vec<String> = tour_config.sessions()[0]
+            .commands()
+            .iter()
+            .map(|c| c.deployment_name())
+            .collect();
+
+        assert_eq!(
+            names,
+            vec![
+                "SRE10-tour-o2",
+                "SRE10-tour-p2",
+                "SRE10-tour-webapp",
+                "custom"
+            ]
+        );
+    }
+
+    #[test]
+    fn written_configs_leave_out_unset_settings() {
+        let yaml = "
+sessions:
+  - name: SRE10
+    commands:
+      - cli:
+          subcommand: group delete
+          parameters:
+            no-wait: ~
+";
+        let tour_config: TourConfig = serde_yaml::from_str(yaml).unwrap();
+        let mut document = serde_yaml::to_value(&tour_config).unwrap();
+        without_unset(&mut document);
+
+        assert_eq!(document, serde_yaml::from_str::<Value>(yaml).unwrap());
+    }
 }
@@ -0,0 +1,322 @@
+use super::{ArmTemplate, Command, FileDownload, TourConfig};
+use reqwest::Url;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+
+// A semantic problem in a configuration that parsed, with where in the document it is.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Problem {
+    pub location: String,
+    pub message: String,
+}
+
+impl Problem {
+    fn new(location: &str, message: String) -> Self {
+        Problem {
+            location: location.to_string(),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+// Local paths are checked relative to the directory `demo up` resolves them in, which is the
+// current directory.
+pub fn validate(tour_config: &TourConfig, directory: &Path) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let mut names = HashSet::new();
+
+    for (i, session) in tour_config.sessions().iter().enumerate() {
+        let location = match session.name {
+            Some(ref name) => format!("sessions[{}] ({})", i, name),
+            None => format!("sessions[{}]", i),
+        };
+
+        match session.name {
+            Some(ref name) if !names.insert(name.to_string()) => problems.push(Problem::new(
+                &location,
+                format!("the session name {} is used more than once", name),
+            )),
+            Some(_) => {}
+            None => problems.push(Problem::new(
+                &location,
+                "a session needs a name".to_string(),
+            )),
+        }
+
+        if let Some(ref slides) = session.slides {
+            check_download(
+                &format!("{}.slides", location),
+                slides,
+                directory,
+                &mut problems,
+            );
+        }
+        for (j, video) in session
+            .videos
+            .clone()
+            .unwrap_or_default()
+            .iter()
+            .enumerate()
+        {
+            check_download(
+                &format!("{}.videos[{}]", location, j),
+                video,
+                directory,
+                &mut problems,
+            );
+        }
+        for (j, repo) in session
+            .git_repos
+            .clone()
+            .unwrap_or_default()
+            .iter()
+            .enumerate()
+        {
+            let location = format!("{}.git_repos[{}]", location, j);
+            match repo.url {
+                Some(ref url) => {
+                    check_source(&format!("{}.url", location), url, directory, &mut problems)
+                }
+                None => problems.push(Problem::new(&location, "a url is required".to_string())),
+            }
+        }
+        for (j, command) in session.commands().iter().enumerate() {
+            check_command(
+                &format!("{}.commands[{}]", location, j),
+                command,
+                directory,
+                &mut problems,
+            );
+        }
+    }
+
+    problems
+}
+
+fn check_download(
+    location: &str,
+    download: &FileDownload,
+    directory: &Path,
+    problems: &mut Vec<Problem>,
+) {
+    match download.url {
+        Some(ref url) => check_source(&format!("{}.url", location), url, directory, problems),
+        None => problems.push(Problem::new(location, "a url is required".to_string())),
+    }
+}
+
+// Slides, videos and repositories can also be local, like the ones in a bundle.
+fn check_source(location: &str, source: &str, directory: &Path, problems: &mut Vec<Problem>) {
+    if !directory.join(source).exists() {
+        check_url(location, source, problems);
+    }
+}
+
+fn check_url(location: &str, url: &str, problems: &mut Vec<Problem>) {
+    if let Err(e) = Url::parse(url) {
+        problems.push(Problem::new(
+            location,
+            format!("{} is not a valid URL ({})", url, e),
+        ));
+    }
+}
+
+fn check_command(location: &str, command: &Command, directory: &Path, problems: &mut Vec<Problem>) {
+    match (&command.cli, &command.template) {
+        (Some(_), Some(_)) => problems.push(Problem::new(
+            location,
+            "a command can have a cli or a template, not both".to_string(),
+        )),
+        (None, None) => problems.push(Problem::new(
+            location,
+            "a command needs either a cli or a template".to_string(),
+        )),
+        (Some(cli), None) if cli.subcommand.is_none() => problems.push(Problem::new(
+            &format!("{}.cli", location),
+            "a cli command needs a subcommand".to_string(),
+        )),
+        (_, Some(template)) => check_template(
+            &format!("{}.template", location),
+            template,
+            directory,
+            problems,
+        ),
+        _ => {}
+    }
+}
+
+fn check_template(
+    location: &str,
+    template: &ArmTemplate,
+    directory: &Path,
+    problems: &mut Vec<Problem>,
+) {
+    match (&template.path, &template.url) {
+        (Some(_), Some(_)) => problems.push(Problem::new(
+            location,
+            "a template can have a path or a url, not both".to_string(),
+        )),
+        (None, None) => problems.push(Problem::new(
+            location,
+            "a template needs either a path or a url".to_string(),
+        )),
+        (Some(path), None) => check_path(&format!("{}.path", location), path, directory, problems),
+        (None, Some(url)) => check_url(&format!("{}.url", location), url, problems),
+    }
+
+    if let Some(ref file) = template.parameters_file {
+        // Parameters files can be local or remote.
+        if Url::parse(file).is_err() {
+            check_path(
+                &format!("{}.parameters_file", location),
+                file,
+                directory,
+                problems,
+            );
+        }
+    }
+}
+
+fn check_path(location: &str, path: &str, directory: &Path, problems: &mut Vec<Problem>) {
+    if !directory.join(path).is_file() {
+        problems.push(Problem::new(location, format!("{} does not exist", path)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CliCommand, GitRepo, Session};
+
+    fn session(name: &str, commands: Vec<Command>) -> Session {
+        Session {
+            name: Some(name.to_string()),
+            commands: Some(commands),
+            ..Default::default()
+        }
+    }
+
+    fn template_command(path: &str) -> Command {
+        let template = ArmTemplate {
+            path: Some(path.to_string()),
+            ..Default::default()
+        };
+        Command {
+            template: Some(template),
+            ..Default::default()
+        }
+    }
+
+    fn problems_for(sessions: Vec<Session>) -> Vec<String> {
+        let tour_config = TourConfig {
+            sessions: Some(sessions),
+            ..Default::default()
+        };
+        validate(&tour_config, Path::new(""))
+            .iter()
+            .map(|p| p.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn valid_config_has_no_problems() {
+        let problems = problems_for(vec![session(
+            "SRE10",
+            vec![template_command("./test/artifacts/demo_template.json")],
+        )]);
+
+        assert!(problems.is_empty());
+    }
+
+    #[test]
+    fn duplicate_session_names_are_problems() {
+        let problems = problems_for(vec![session("SRE10", vec![]), session("SRE10", vec![])]);
+
+        assert_eq!(
+            problems,
+            vec!["sessions[1] (SRE10): the session name SRE10 is used more than once"]
+        );
+    }
+
+    #[test]
+    fn commands_need_exactly_one_of_cli_and_template() {
+        let cli = CliCommand {
+            subcommand: Some("webapp list".to_string()),
+            ..Default::default()
+        };
+        let both = Command {
+            cli: Some(cli),
+            ..template_command("./test/artifacts/demo_template.json")
+        };
+
+        let problems = problems_for(vec![session("SRE10", vec![Command::default(), both])]);
+
+        assert_eq!(
+            problems,
+            vec![
+                "sessions[0] (SRE10).commands[0]: a command needs either a cli or a template",
+                "sessions[0] (SRE10).commands[1]: a command can have a cli or a template, not both"
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_template_paths_are_problems() {
+        let problems = problems_for(vec![session(
+            "SRE10",
+            vec![template_command("./test/artifacts/missing.json")],
+        )]);
+
+        assert_eq!(
+            problems,
+            vec!["sessions[0] (SRE10).commands[0].template.path: ./test/artifacts/missing.json does not exist"]
+        );
+    }
+
+    #[test]
+    fn invalid_urls_are_problems() {
+        let slides = FileDownload {
+            url: Some("not a url".to_string()),
+            ..Default::default()
+        };
+        let session = Session {
+            slides: Some(slides),
+            git_repos: Some(vec![GitRepo::new("https://github.com/microsoft/ignite")]),
+            ..session("SRE10", vec![])
+        };
+
+        let problems = problems_for(vec![session]);
+
+        assert_eq!(problems.len(), 1);
+        assert!(
+            problems[0].starts_with("sessions[0] (SRE10).slides.url: not a url is not a valid URL")
+        );
+    }
+
+    #[test]
+    fn existing_local_sources_are_valid() {
+        let slides = FileDownload {
+            url: Some("environment.json".to_string()),
+            ..Default::default()
+        };
+        let session = Session {
+            slides: Some(slides),
+            git_repos: Some(vec![GitRepo::new("includes")]),
+            ..session("SRE10", vec![template_command("demo_template.json")])
+        };
+        let tour_config = TourConfig {
+            sessions: Some(vec![session]),
+            ..Default::default()
+        };
+
+        assert!(validate(&tour_config, Path::new("./test/artifacts")).is_empty());
+        assert_eq!(validate(&tour_config, Path::new("")).len(), 3);
+    }
+}
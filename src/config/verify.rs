@@ -0,0 +1,191 @@
+use super::{read_from_url, ConfigError};
+use crate::tasks::confirm;
+use minisign_verify::{PublicKey, Signature};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+
+// How configuration files are checked before they are used.  A pinned SHA-256 only applies to
+// the file given on the command line, signatures apply to every remote file.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Verification {
+    pub sha256: Option<String>,
+    pub public_key: Option<String>,
+    pub allow_unsigned: bool,
+}
+
+impl Verification {
+    pub fn verify(&self, path: &str, content: &str, root: bool) -> Result<(), ConfigError> {
+        if root {
+            if let Some(ref expected) = self.sha256 {
+                return check_sha256(path, content, expected);
+            }
+        }
+        // Local files are as trustworthy as the machine running `demo`.
+        let url = match Url::parse(path) {
+            Ok(u) => u,
+            Err(_) => return Ok(()),
+        };
+
+        // Once a key is given, a file without a readable signature is never used: whoever can
+        // change the file can also remove its signature.
+        if let Some(ref key) = self.public_key {
+            let public_key = decode_public_key(key)?;
+            // Signatures are detached, next to the file.
+            let signature_url = format!("{}.minisig", url);
+            let signature = Url::parse(&signature_url)
+                .map_err(|e| e.to_string())
+                .and_then(|u| read_from_url(u).map_err(|e| e.to_string()))
+                .map_err(|e| ConfigError::InvalidSignature {
+                    path: path.to_string(),
+                    message: format!("failed to read {} ({})", signature_url, e),
+                })?;
+            return check_signature(path, content, &signature, &public_key);
+        }
+
+        eprintln!(
+            "Warning: {} is not signed, so it cannot be verified.  It may run any Azure CLI command or deploy any template.",
+            path
+        );
+        if self.allow_unsigned || confirm("Use the unsigned configuration file anyway?") {
+            Ok(())
+        } else {
+            Err(ConfigError::Unverified {
+                path: path.to_string(),
+            })
+        }
+    }
+}
+
+pub fn sha256(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+fn check_sha256(path: &str, content: &str, expected: &str) -> Result<(), ConfigError> {
+    let actual = sha256(content);
+    if actual.eq_ignore_ascii_case(expected.trim()) {
+        Ok(())
+    } else {
+        Err(ConfigError::ChecksumMismatch {
+            path: path.to_string(),
+            expected: expected.to_string(),
+            actual,
+        })
+    }
+}
+
+// The key can be the key itself or a minisign public key file.
+fn decode_public_key(key: &str) -> Result<PublicKey, ConfigError> {
+    let decoded = if Path::new(key).is_file() {
+        fs::read_to_string(key)
+            .map_err(|e| e.to_string())
+            .and_then(|k| PublicKey::decode(&k).map_err(|e| e.to_string()))
+    } else {
+        PublicKey::from_base64(key.trim()).map_err(|e| e.to_string())
+    };
+    decoded.map_err(|message| ConfigError::InvalidPublicKey { message })
+}
+
+fn check_signature(
+    path: &str,
+    content: &str,
+    signature: &str,
+    public_key: &PublicKey,
+) -> Result<(), ConfigError> {
+    let invalid = |message: String| ConfigError::InvalidSignature {
+        path: path.to_string(),
+        message,
+    };
+    let signature = Signature::decode(signature).map_err(|e| invalid(e.to_string()))?;
+    public_key
+        .verify(content.as_bytes(), &signature, false)
+        .map_err(|e| invalid(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBLIC_KEY: &str = "./test/artifacts/signing/demo.pub";
+
+    fn signed_config() -> (String, String) {
+        (
+            fs::read_to_string("./test/artifacts/signing/demo.yml").unwrap(),
+            fs::read_to_string("./test/artifacts/signing/demo.yml.minisig").unwrap(),
+        )
+    }
+
+    #[test]
+    fn pinned_checksums_must_match() {
+        let (content, _) = signed_config();
+        let verification = Verification {
+            sha256: Some(sha256(&content).to_uppercase()),
+            ..Default::default()
+        };
+
+        assert!(verification
+            .verify("https://example.com/demo.yml", &content, true)
+            .is_ok());
+        match verification.verify("https://example.com/demo.yml", "location: eastus", true) {
+            Err(ConfigError::ChecksumMismatch { actual, .. }) => {
+                assert_eq!(actual, sha256("location: eastus"))
+            }
+            r => panic!("Expected a checksum mismatch, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn signatures_are_checked_against_the_public_key() {
+        let (content, signature) = signed_config();
+        let public_key = decode_public_key(PUBLIC_KEY).unwrap();
+
+        assert!(check_signature("demo.yml", &content, &signature, &public_key).is_ok());
+        assert!(check_signature(
+            "demo.yml",
+            &content.replace("westus2", "eastus"),
+            &signature,
+            &public_key
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn public_keys_can_be_given_inline() {
+        let file = fs::read_to_string(PUBLIC_KEY).unwrap();
+        let key = file.lines().nth(1).unwrap();
+
+        assert!(decode_public_key(key).is_ok());
+        assert!(decode_public_key("not a key").is_err());
+    }
+
+    #[test]
+    fn missing_signatures_are_errors_once_a_key_is_given() {
+        let (content, _) = signed_config();
+        let verification = Verification {
+            public_key: Some(PUBLIC_KEY.to_string()),
+            allow_unsigned: true,
+            ..Default::default()
+        };
+
+        match verification.verify("http://127.0.0.1:1/demo.yml", &content, true) {
+            Err(ConfigError::InvalidSignature { message, .. }) => {
+                assert!(message.contains("http://127.0.0.1:1/demo.yml.minisig"))
+            }
+            r => panic!("Expected an invalid signature, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn local_files_do_not_need_signatures() {
+        let verification = Verification {
+            public_key: Some(PUBLIC_KEY.to_string()),
+            ..Default::default()
+        };
+
+        assert!(verification
+            .verify("./test/artifacts/signing/demo.yml", "", false)
+            .is_ok());
+    }
+}
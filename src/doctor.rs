@@ -0,0 +1,430 @@
+use crate::config::{get_config, ConfigError, TourConfig};
+use crate::tasks::az_cli::{
+    can_access_subscription, find_command, get_account_info, get_az_version, AzCliError, AzVersion,
+};
+use crate::tasks::{get_content_length, use_isolated_azure_config};
+use custom_error::custom_error;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fmt;
+use std::fs::{remove_file, File};
+use std::io::prelude::*;
+use std::path::Path;
+
+// ARM template previews (`az deployment group what-if`) need at least this version.
+const MINIMUM_AZ_VERSION: &str = "2.5.0";
+const TLS_CHECK_URL: &str = "https://management.azure.com";
+const MEGABYTE: u64 = 1024 * 1024;
+
+custom_error! {
+    pub DemoDoctorError
+    ConfigFailure{source: ConfigError} = "{source}",
+    BlockingProblems{count: usize} = "Found {count} blocking problem(s) in the environment.",
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let label = match self {
+            CheckStatus::Pass => "PASS",
+            CheckStatus::Warn => "WARN",
+            CheckStatus::Fail => "FAIL",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CheckResult {
+    pub name: String,
+    pub status: CheckStatus,
+    pub message: String,
+}
+
+impl CheckResult {
+    fn new(name: &str, status: CheckStatus, message: String) -> Self {
+        CheckResult {
+            name: name.to_string(),
+            status,
+            message,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DoctorCommand {
+    pub config_path: String,
+    pub subscription: String,
+    pub event: Option<String>,
+    pub azure_profile: Option<String>,
+    pub shared_azure_config: bool,
+}
+
+impl DoctorCommand {
+    fn azure_profile(&self) -> Option<String> {
+        self.azure_profile.clone().or_else(|| self.event.clone())
+    }
+
+    fn subscription(&self, tour_config: &TourConfig) -> Option<String> {
+        if !self.subscription.is_empty() {
+            Some(self.subscription.clone())
+        } else {
+            tour_config.subscription.clone()
+        }
+    }
+
+    pub fn execute(&self) -> Result<(), DemoDoctorError> {
+        println!("Loading the configuration from {}\n", &self.config_path);
+        let tour_config = get_config(&self.config_path)?;
+
+        let mut results = Vec::new();
+        results.extend(self.check_azure_cli(&tour_config));
+        results.push(check_git());
+        let directory = env::current_dir().unwrap();
+        results.push(check_output_directory(&directory));
+        results.push(check_disk_space(
+            fs2::available_space(&directory).ok(),
+            expected_download_size(&tour_config),
+        ));
+        results.push(check_tls());
+
+        for result in &results {
+            println!("[{}] {}: {}", result.status, result.name, result.message);
+        }
+
+        let count = blocking_problems(&results);
+        if count > 0 {
+            Err(DemoDoctorError::BlockingProblems { count })
+        } else {
+            println!("\nThe environment is ready.");
+            Ok(())
+        }
+    }
+
+    fn check_azure_cli(&self, tour_config: &TourConfig) -> Vec<CheckResult> {
+        let path = match find_command("az") {
+            Some(p) => p,
+            None => {
+                return vec![CheckResult::new(
+                    "Azure CLI",
+                    CheckStatus::Fail,
+                    "Unable to find `az` on the PATH.".to_string(),
+                )]
+            }
+        };
+        let mut results = vec![CheckResult::new(
+            "Azure CLI",
+            CheckStatus::Pass,
+            format!("Found {}", path.display()),
+        )];
+
+        if !self.shared_azure_config {
+            if let Some(profile) = self.azure_profile() {
+                if let Ok(directory) = use_isolated_azure_config(&profile) {
+                    println!("Using the Azure CLI profile in {}\n", directory.display());
+                }
+            }
+        }
+
+        match get_az_version() {
+            Ok(version) => {
+                results.push(check_az_version(&version.cli));
+                results.push(check_extensions(&tour_config.extensions(), &version));
+            }
+            Err(e) => results.push(CheckResult::new(
+                "Azure CLI version",
+                CheckStatus::Fail,
+                format!(
+                    "Unable to read the version ({}).  Version {} or newer is required.",
+                    e, MINIMUM_AZ_VERSION
+                ),
+            )),
+        }
+
+        match get_account_info() {
+            Ok(account) => {
+                results.push(CheckResult::new(
+                    "Azure login",
+                    CheckStatus::Pass,
+                    format!(
+                        "Logged in to tenant {}",
+                        account.tenant_id.unwrap_or_default()
+                    ),
+                ));
+                results.push(self.check_subscription(tour_config));
+            }
+            Err(AzCliError::NotLoggedIn) => results.push(CheckResult::new(
+                "Azure login",
+                CheckStatus::Warn,
+                "Not logged in.  `demo up` will log in before provisioning.".to_string(),
+            )),
+            Err(e) => results.push(CheckResult::new(
+                "Azure login",
+                CheckStatus::Fail,
+                e.to_string(),
+            )),
+        }
+
+        results
+    }
+
+    fn check_subscription(&self, tour_config: &TourConfig) -> CheckResult {
+        let subscription = match self.subscription(tour_config) {
+            Some(s) => s,
+            None => {
+                return CheckResult::new(
+                    "Subscription",
+                    CheckStatus::Fail,
+                    "No subscription in the configuration file or on the command line.".to_string(),
+                )
+            }
+        };
+
+        match can_access_subscription(&subscription) {
+            Ok(true) => CheckResult::new(
+                "Subscription",
+                CheckStatus::Pass,
+                format!("Able to access {}", subscription),
+            ),
+            Ok(false) => CheckResult::new(
+                "Subscription",
+                CheckStatus::Fail,
+                format!("The current login can not access {}", subscription),
+            ),
+            Err(e) => CheckResult::new("Subscription", CheckStatus::Fail, e.to_string()),
+        }
+    }
+}
+
+fn parse_version(version: &str) -> Vec<u32> {
+    version
+        .trim()
+        .split('.')
+        .map(|part| {
+            part.chars()
+                .take_while(|c| c.is_ascii_digit())
+                .collect::<String>()
+                .parse()
+                .unwrap_or(0)
+        })
+        .collect()
+}
+
+fn check_az_version(version: &str) -> CheckResult {
+    if parse_version(version) >= parse_version(MINIMUM_AZ_VERSION) {
+        CheckResult::new(
+            "Azure CLI version",
+            CheckStatus::Pass,
+            format!("Version {}", version),
+        )
+    } else {
+        CheckResult::new(
+            "Azure CLI version",
+            CheckStatus::Fail,
+            format!(
+                "Version {} is older than the required {}.  Run `az upgrade`.",
+                version, MINIMUM_AZ_VERSION
+            ),
+        )
+    }
+}
+
+fn check_extensions(required: &[String], version: &AzVersion) -> CheckResult {
+    let missing: Vec<&str> = required
+        .iter()
+        .filter(|e| !version.extensions.contains_key(e.as_str()))
+        .map(|e| e.as_str())
+        .collect();
+
+    if missing.is_empty() {
+        CheckResult::new(
+            "Azure CLI extensions",
+            CheckStatus::Pass,
+            format!("{} required extension(s) installed", required.len()),
+        )
+    } else {
+        CheckResult::new(
+            "Azure CLI extensions",
+            CheckStatus::Fail,
+            format!(
+                "Missing {}.  Install with `az extension add --name <extension>`.",
+                missing.join(", ")
+            ),
+        )
+    }
+}
+
+// Repositories are cloned with libgit2, so a missing git is only an inconvenience.
+fn check_git() -> CheckResult {
+    match find_command("git") {
+        Some(path) => CheckResult::new(
+            "git",
+            CheckStatus::Pass,
+            format!("Found {}", path.display()),
+        ),
+        None => CheckResult::new(
+            "git",
+            CheckStatus::Warn,
+            "Unable to find `git` on the PATH.".to_string(),
+        ),
+    }
+}
+
+fn check_output_directory(directory: &Path) -> CheckResult {
+    let probe = directory.join(".demo-up-doctor");
+    let writable = File::create(&probe)
+        .and_then(|mut f| f.write_all(b"demo-up"))
+        .and_then(|_| remove_file(&probe));
+
+    match writable {
+        Ok(_) => CheckResult::new(
+            "Output directory",
+            CheckStatus::Pass,
+            format!("{} is writable", directory.display()),
+        ),
+        Err(e) => CheckResult::new(
+            "Output directory",
+            CheckStatus::Fail,
+            format!("Unable to write to {} ({})", directory.display(), e),
+        ),
+    }
+}
+
+// The total size of the slides and videos, as reported by the servers hosting them.
+fn expected_download_size(tour_config: &TourConfig) -> u64 {
+    let mut urls = Vec::new();
+    for s in tour_config.sessions() {
+        if let Some(slides) = s.slides {
+            urls.extend(slides.url);
+        }
+        for video in s.videos.unwrap_or_default() {
+            urls.extend(video.url);
+        }
+    }
+
+    urls.iter().filter_map(|u| get_content_length(u)).sum()
+}
+
+fn check_disk_space(available: Option<u64>, expected: u64) -> CheckResult {
+    match available {
+        Some(a) if a >= expected => CheckResult::new(
+            "Disk space",
+            CheckStatus::Pass,
+            format!(
+                "{} MB free, {} MB of downloads expected",
+                a / MEGABYTE,
+                expected / MEGABYTE
+            ),
+        ),
+        Some(a) => CheckResult::new(
+            "Disk space",
+            CheckStatus::Fail,
+            format!(
+                "Only {} MB free, but {} MB of downloads are expected",
+                a / MEGABYTE,
+                expected / MEGABYTE
+            ),
+        ),
+        None => CheckResult::new(
+            "Disk space",
+            CheckStatus::Warn,
+            "Unable to determine the free disk space.".to_string(),
+        ),
+    }
+}
+
+// Any HTTP response (even an error status) means the TLS handshake succeeded.
+fn check_tls() -> CheckResult {
+    match Client::new().head(TLS_CHECK_URL).send() {
+        Ok(_) => CheckResult::new(
+            "TLS",
+            CheckStatus::Pass,
+            format!("Connected to {}", TLS_CHECK_URL),
+        ),
+        Err(e) => CheckResult::new(
+            "TLS",
+            CheckStatus::Fail,
+            format!("Unable to connect to {} ({})", TLS_CHECK_URL, e),
+        ),
+    }
+}
+
+fn blocking_problems(results: &[CheckResult]) -> usize {
+    results
+        .iter()
+        .filter(|r| r.status == CheckStatus::Fail)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn version_with_extensions(extensions: &[&str]) -> AzVersion {
+        let mut installed = HashMap::new();
+        for e in extensions {
+            installed.insert(e.to_string(), "1.0.0".to_string());
+        }
+        AzVersion {
+            cli: "2.10.1".to_string(),
+            extensions: installed,
+        }
+    }
+
+    #[test]
+    fn versions_compare_numerically() {
+        assert_eq!(check_az_version("2.10.1").status, CheckStatus::Pass);
+        assert_eq!(check_az_version("2.5.0").status, CheckStatus::Pass);
+        assert_eq!(check_az_version("2.4.0").status, CheckStatus::Fail);
+        assert_eq!(check_az_version("2.0.81").status, CheckStatus::Fail);
+    }
+
+    #[test]
+    fn prerelease_versions_are_parsed() {
+        assert_eq!(parse_version("2.6.0b1"), vec![2, 6, 0]);
+    }
+
+    #[test]
+    fn missing_extensions_fail() {
+        let version = version_with_extensions(&["azure-devops"]);
+        let required = vec!["azure-devops".to_string(), "aks-preview".to_string()];
+
+        let result = check_extensions(&required, &version);
+
+        assert_eq!(result.status, CheckStatus::Fail);
+        assert!(result.message.contains("aks-preview"));
+        assert!(!result.message.contains("azure-devops"));
+    }
+
+    #[test]
+    fn disk_space_is_compared_with_downloads() {
+        assert_eq!(
+            check_disk_space(Some(10 * MEGABYTE), MEGABYTE).status,
+            CheckStatus::Pass
+        );
+        assert_eq!(
+            check_disk_space(Some(MEGABYTE), 10 * MEGABYTE).status,
+            CheckStatus::Fail
+        );
+        assert_eq!(check_disk_space(None, MEGABYTE).status, CheckStatus::Warn);
+    }
+
+    #[test]
+    fn only_failures_are_blocking() {
+        let results = vec![
+            CheckResult::new("a", CheckStatus::Pass, String::new()),
+            CheckResult::new("b", CheckStatus::Warn, String::new()),
+            CheckResult::new("c", CheckStatus::Fail, String::new()),
+        ];
+
+        assert_eq!(blocking_problems(&results), 1);
+    }
+}
@@ -1,4 +1,6 @@
-use crate::config::get_config;
+use crate::config::tags::without_tool_tags;
+use crate::config::{get_verified_config, ConfigError, TourConfig, Verification};
+use crate::up::UpCommand;
 use custom_error::custom_error;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
@@ -6,12 +8,18 @@ use std::path::PathBuf;
 custom_error! {
     pub DemoFetchError
     Failed = "Failed to retrieve the configuration file.",
+    ConfigFailure{source: ConfigError} = "{source}",
+    NoSessions{session_names: String} = "The configuration file has none of the sessions {session_names}.",
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct FetchCommand {
     pub source_config_file_path: String,
     pub output_file_path: PathBuf,
+    pub profile: Option<String>,
+    pub verification: Verification,
+    pub session_names: Option<Vec<String>>,
+    pub event: Option<String>,
 }
 
 impl FetchCommand {
@@ -19,11 +27,115 @@ impl FetchCommand {
         FetchCommand {
             source_config_file_path,
             output_file_path: PathBuf::from(output_file_path),
+            profile: None,
+            verification: Verification::default(),
+            session_names: None,
+            event: None,
         }
     }
     pub fn execute(&self) -> Result<(), DemoFetchError> {
-        let config = get_config(&self.source_config_file_path);
+        let mut config = get_verified_config(
+            &self.source_config_file_path,
+            self.profile.as_deref(),
+            &self.verification,
+        )?;
+        if let Some(ref session_names) = self.session_names {
+            config = resolve_sessions(config, session_names, self.event.clone())?;
+        }
         config.write(&self.output_file_path);
         Ok(())
     }
 }
+
+// Keeps only the selected sessions, with the names, locations and subscriptions `demo up` would
+// use filled in, so the file shows exactly what will be set up.  Profiles are dropped, since they
+// no longer apply once everything is resolved.
+pub fn resolve_sessions(
+    mut config: TourConfig,
+    session_names: &[String],
+    event: Option<String>,
+) -> Result<TourConfig, DemoFetchError> {
+    if config.filter_sessions(session_names).is_empty() {
+        return Err(DemoFetchError::NoSessions {
+            session_names: session_names.join(", "),
+        });
+    }
+
+    let cli_args = UpCommand {
+        event,
+        session_names: Some(session_names.to_vec()),
+        ..Default::default()
+    };
+    config.update(&cli_args);
+    config.profiles = None;
+
+    if let Some(ref mut sessions) = config.sessions {
+        for command in sessions
+            .iter_mut()
+            .flat_map(|s| s.commands.iter_mut().flatten())
+        {
+            command.tags = command
+                .tags
+                .as_ref()
+                .map(without_tool_tags)
+                .filter(|t| !t.is_empty());
+        }
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::read_config;
+    use std::env;
+    use std::fs;
+
+    fn fetch(
+        session_names: Vec<&str>,
+        profile: Option<&str>,
+    ) -> Result<TourConfig, DemoFetchError> {
+        let output = env::temp_dir().join(format!("demo-up-fetch-{}.yml", session_names.join("-")));
+        let mut fetch = FetchCommand::new(
+            "./test/artifacts/profiles_config.yml".to_string(),
+            output.to_string_lossy().to_string(),
+        );
+        fetch.session_names = Some(session_names.iter().map(|s| s.to_string()).collect());
+        fetch.event = Some("tour".to_string());
+        fetch.profile = profile.map(|p| p.to_string());
+
+        fetch.execute()?;
+        let config = read_config(&output.to_string_lossy()).unwrap();
+        fs::remove_file(&output).unwrap();
+        Ok(config)
+    }
+
+    #[test]
+    fn fetch_writes_only_the_selected_sessions_resolved() {
+        let config = fetch(vec!["SRE10", "SRE50"], Some("rehearsal")).unwrap();
+        let sessions = config.sessions();
+        let command = &sessions[0].commands()[0];
+
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].name(), "SRE10");
+        assert_eq!(config.profiles, None);
+        assert_eq!(command.resource_group, Some("SRE10-tour".to_string()));
+        assert_eq!(command.location, Some("eastus".to_string()));
+        assert_eq!(command.subscription, Some("demo".to_string()));
+        assert_eq!(
+            command.deployment_name,
+            Some("SRE10-tour-webapp".to_string())
+        );
+        assert_eq!(command.tags, None);
+    }
+
+    #[test]
+    fn fetch_needs_one_of_the_sessions() {
+        match fetch(vec!["DEV10"], None) {
+            Err(DemoFetchError::NoSessions { session_names }) => {
+                assert_eq!(session_names, "DEV10")
+            }
+            r => panic!("Expected no sessions, got {:?}", r.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use crate::config::tags::{parse_timestamp, CREATED_TAG, EVENT_TAG, EXPIRES_TAG};
+use crate::tasks::backend::{CloudBackendError, ResourceGroup, ACCESS_TOKEN_VARIABLE};
+use crate::tasks::*;
+use chrono::{DateTime, Duration, Utc};
+use custom_error::custom_error;
+use serde::{Deserialize, Serialize};
+use std::env;
+
+custom_error! {
+    pub DemoGcError
+    Failed = "Failed to clean up the expired resource groups.",
+    BackendFailure{source: CloudBackendError} = "{source}",
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GcCommand {
+    pub subscription: String,
+    pub credential: CredentialOptions,
+    pub event: Option<String>,
+    // Whether only the resource groups of the event are considered, rather than those of every
+    // event (the event always picks the Azure CLI profile).
+    pub only_event: bool,
+    pub backend: String,
+    pub azure_profile: Option<String>,
+    pub shared_azure_config: bool,
+    pub yes: bool,
+    pub dry_run: bool,
+    pub older_than: Option<u32>,
+}
+
+impl GcCommand {
+    fn azure_profile(&self) -> Option<String> {
+        self.azure_profile.clone().or_else(|| self.event.clone())
+    }
+
+    pub fn execute(&self) -> Result<(), DemoGcError> {
+        if !self.shared_azure_config {
+            if let Some(profile) = self.azure_profile() {
+                let directory =
+                    use_isolated_azure_config(&profile).map_err(CloudBackendError::from)?;
+                println!("Using the Azure CLI profile in {}\n", directory.display());
+            }
+        }
+        if self.backend == AZ_CLI_BACKEND || env::var(ACCESS_TOKEN_VARIABLE).is_err() {
+            set_azure_environment(&self.subscription, &self.credential)
+                .map_err(CloudBackendError::from)?;
+        }
+        let backend = get_backend(&self.backend, &self.subscription)?;
+
+        let now = Utc::now();
+        let older_than = self.older_than.map(|h| Duration::hours(i64::from(h)));
+        let event = if self.only_event {
+            self.event.as_deref()
+        } else {
+            None
+        };
+        let expired: Vec<ResourceGroup> = backend
+            .list_resource_groups(EVENT_TAG)?
+            .into_iter()
+            .filter(|g| is_event(g, event) && is_expired(g, now, older_than))
+            .collect();
+
+        if expired.is_empty() {
+            println!("No expired resource groups found.");
+            return Ok(());
+        }
+
+        println!("Found {} expired resource group(s):", expired.len());
+        for group in &expired {
+            let tags = group.tags();
+            println!(
+                "\t{} ({}) event: {}, expired: {}",
+                group.name,
+                group.location,
+                tags.get(EVENT_TAG).map(|e| e.as_str()).unwrap_or(""),
+                tags.get(EXPIRES_TAG).map(|e| e.as_str()).unwrap_or("")
+            );
+        }
+
+        if self.dry_run {
+            println!("\nDry run, nothing was deleted.");
+            return Ok(());
+        }
+        if !self.yes && !confirm("\nDelete these resource groups?") {
+            println!("Nothing was deleted.");
+            return Ok(());
+        }
+
+        for group in &expired {
+            println!("\tDeleting {}", group.name);
+            backend.delete_resource_group(&group.name)?;
+        }
+        Ok(())
+    }
+}
+
+fn is_event(group: &ResourceGroup, event: Option<&str>) -> bool {
+    match event {
+        Some(e) => group.tags().get(EVENT_TAG).map(|t| t.as_str()) == Some(e),
+        None => true,
+    }
+}
+
+// Only resource groups created by the tool are considered.  They expire at their expiry tag, or
+// when they were created longer ago than the override.
+fn is_expired(group: &ResourceGroup, now: DateTime<Utc>, older_than: Option<Duration>) -> bool {
+    let tags = group.tags();
+    if !tags.contains_key(EVENT_TAG) {
+        return false;
+    }
+    match older_than {
+        Some(age) => matches!(parse_timestamp(&tags, CREATED_TAG), Some(c) if c + age < now),
+        None => matches!(parse_timestamp(&tags, EXPIRES_TAG), Some(e) if e < now),
+    }
+}
+
+impl Default for GcCommand {
+    fn default() -> GcCommand {
+        GcCommand {
+            subscription: "".to_string(),
+            credential: CredentialOptions::default(),
+            event: None,
+            only_event: false,
+            backend: AZ_CLI_BACKEND.to_string(),
+            azure_profile: None,
+            shared_azure_config: false,
+            yes: false,
+            dry_run: false,
+            older_than: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Tags;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2019, 11, 10, 12, 0, 0).unwrap()
+    }
+
+    fn group(tags: &[(&str, &str)]) -> ResourceGroup {
+        let mut t = Tags::new();
+        for (k, v) in tags {
+            t.insert(k.to_string(), v.to_string());
+        }
+        ResourceGroup {
+            name: "SRE10-tour".to_string(),
+            location: "westus2".to_string(),
+            tags: Some(t),
+        }
+    }
+
+    #[test]
+    fn groups_past_their_expiry_are_expired() {
+        let expired = group(&[(EVENT_TAG, "tour"), (EXPIRES_TAG, "2019-11-09T12:00:00Z")]);
+        let current = group(&[(EVENT_TAG, "tour"), (EXPIRES_TAG, "2019-11-11T12:00:00Z")]);
+
+        assert!(is_expired(&expired, now(), None));
+        assert!(!is_expired(&current, now(), None));
+    }
+
+    #[test]
+    fn only_groups_created_by_the_tool_are_expired() {
+        let foreign = group(&[(EXPIRES_TAG, "2019-11-09T12:00:00Z")]);
+        let untagged = ResourceGroup {
+            tags: None,
+            ..foreign.clone()
+        };
+        let unreadable = group(&[(EVENT_TAG, "tour"), (EXPIRES_TAG, "soon")]);
+
+        assert!(!is_expired(&foreign, now(), None));
+        assert!(!is_expired(&untagged, now(), None));
+        assert!(!is_expired(&unreadable, now(), None));
+    }
+
+    #[test]
+    fn only_groups_of_the_event_are_considered() {
+        let tour = group(&[(EVENT_TAG, "tour"), (EXPIRES_TAG, "2019-11-09T12:00:00Z")]);
+        let rehearsal = group(&[
+            (EVENT_TAG, "rehearsal"),
+            (EXPIRES_TAG, "2019-11-09T12:00:00Z"),
+        ]);
+
+        assert!(is_event(&tour, Some("tour")));
+        assert!(!is_event(&rehearsal, Some("tour")));
+        assert!(is_event(&rehearsal, None));
+    }
+
+    #[test]
+    fn older_than_overrides_the_expiry() {
+        let group = group(&[
+            (EVENT_TAG, "tour"),
+            (CREATED_TAG, "2019-11-09T12:00:00Z"),
+            (EXPIRES_TAG, "2019-11-12T12:00:00Z"),
+        ]);
+
+        assert!(is_expired(&group, now(), Some(Duration::hours(12))));
+        assert!(!is_expired(&group, now(), Some(Duration::hours(48))));
+    }
+}
@@ -1,26 +1,53 @@
+extern crate chrono;
 #[macro_use]
 extern crate clap;
 extern crate custom_error;
+extern crate dirs;
 extern crate duct;
+extern crate fs2;
 extern crate git2;
+extern crate glob;
+extern crate minisign_verify;
 extern crate regex;
 extern crate reqwest;
+extern crate schemars;
 extern crate serde;
 extern crate serde_json;
 extern crate serde_yaml;
-extern crate uuid;
+extern crate sha2;
+extern crate tar;
+extern crate tempfile;
+extern crate zstd;
 
+mod bundle;
 mod cli;
 mod config;
+mod doctor;
 mod fetch;
+mod gc;
+mod migrate;
 mod pkg;
+mod plan;
+mod schema;
+mod status;
 mod tasks;
 mod up;
+mod validate;
+use bundle::BundleCommand;
 use cli::get_app_cli;
+use config::Verification;
 
+use doctor::DoctorCommand;
 use fetch::FetchCommand;
+use gc::GcCommand;
+use migrate::MigrateCommand;
 use pkg::PkgCommand;
+use plan::PlanCommand;
+use schema::SchemaCommand;
+use status::StatusCommand;
+use tasks::CredentialOptions;
 use up::UpCommand;
+use validate::ValidateCommand;
 
 fn main() {
     let version = format!(
@@ -30,43 +57,153 @@ fn main() {
     );
 
     let matches = get_app_cli(&version).get_matches();
+    config::cache::set_offline(matches.is_present("offline"));
 
     if let Some(sub_matches) = matches.subcommand_matches("up") {
-        let mut up_config = UpCommand::default();
-
-        up_config.config_path = get_single_argument(&sub_matches, "config_file");
-        up_config.subscription = get_single_argument(&sub_matches, "subscription");
-        up_config.event = Some(get_single_argument(&sub_matches, "event"));
-
-        if sub_matches.is_present("learning_path") {
-            let learning_paths = get_vec_argument(&sub_matches, "learning_path");
-            let mut sessions = Vec::new();
-            for l in learning_paths {
-                sessions.extend(map_learning_path_to_session_name(&l));
-            }
-            up_config.session_names = Some(sessions);
+        let exclude = if sub_matches.is_present("exclude") {
+            Some(get_vec_argument(sub_matches, "exclude"))
+        } else {
+            None
         };
-
-        if sub_matches.is_present("session_name") {
-            let sessions = get_vec_argument(&sub_matches, "session_name");
-            up_config.session_names = Some(sessions);
-        };
-
-        if sub_matches.is_present("exclude") {
-            let excludes = get_vec_argument(&sub_matches, "exclude");
-            up_config.exclude = Some(excludes);
+        let up_config = UpCommand {
+            config_path: get_single_argument(sub_matches, "config_file"),
+            verification: get_verification(sub_matches),
+            // The subscription may come from the configuration file instead of the command line.
+            subscription: sub_matches
+                .value_of("subscription")
+                .unwrap_or_default()
+                .to_string(),
+            credential: get_credential(sub_matches),
+            event: Some(get_single_argument(sub_matches, "event")),
+            session_names: get_session_names(sub_matches),
+            exclude,
+            profile: get_optional_argument(sub_matches, "profile"),
+            location: get_optional_argument(sub_matches, "location"),
+            allow_unknown_location: sub_matches.is_present("allow_unknown_location"),
+            what_if: sub_matches.is_present("what_if"),
+            confirm: sub_matches.is_present("confirm"),
+            backend: get_single_argument(sub_matches, "backend"),
+            azure_profile: get_optional_argument(sub_matches, "azure_profile"),
+            shared_azure_config: sub_matches.is_present("shared_azure_config"),
+            presenter: get_optional_argument(sub_matches, "presenter"),
+            expires_in: value_t!(sub_matches, "expires_in", u32).unwrap(),
+            ..Default::default()
         };
 
-        up_config.execute().unwrap();
+        exit_on_error(up_config.execute());
     }
 
     if let Some(sub_matches) = matches.subcommand_matches("fetch") {
         let output_file = get_single_argument(&sub_matches, "OUTPUT");
         let config_path = get_single_argument(&sub_matches, "config_file");
-        let fetch_config = FetchCommand::new(config_path, output_file);
-        fetch_config.execute().unwrap();
+        let mut fetch_config = FetchCommand::new(config_path, output_file);
+        fetch_config.profile = get_optional_argument(sub_matches, "profile");
+        fetch_config.verification = get_verification(sub_matches);
+        fetch_config.session_names = get_session_names(sub_matches);
+        fetch_config.event = Some(get_single_argument(sub_matches, "event"));
+        exit_on_error(fetch_config.execute());
     };
 
+    if let Some(sub_matches) = matches.subcommand_matches("bundle") {
+        let output_file = get_single_argument(sub_matches, "output");
+        let config_path = get_single_argument(sub_matches, "config_file");
+        let mut bundle_config = BundleCommand::new(config_path, output_file);
+        bundle_config.profile = get_optional_argument(sub_matches, "profile");
+        bundle_config.verification = get_verification(sub_matches);
+        bundle_config.session_names = get_session_names(sub_matches);
+        bundle_config.event = Some(get_single_argument(sub_matches, "event"));
+        exit_on_error(bundle_config.execute());
+    }
+
+    if let Some(sub_matches) = matches.subcommand_matches("doctor") {
+        let doctor_config = DoctorCommand {
+            config_path: get_single_argument(sub_matches, "config_file"),
+            subscription: sub_matches
+                .value_of("subscription")
+                .unwrap_or_default()
+                .to_string(),
+            event: get_optional_argument(sub_matches, "event"),
+            azure_profile: get_optional_argument(sub_matches, "azure_profile"),
+            shared_azure_config: sub_matches.is_present("shared_azure_config"),
+        };
+
+        exit_on_error(doctor_config.execute());
+    }
+
+    if let Some(sub_matches) = matches.subcommand_matches("gc") {
+        let older_than = if sub_matches.is_present("older_than") {
+            Some(value_t!(sub_matches, "older_than", u32).unwrap())
+        } else {
+            None
+        };
+        let gc_config = GcCommand {
+            subscription: sub_matches
+                .value_of("subscription")
+                .unwrap_or_default()
+                .to_string(),
+            credential: get_credential(sub_matches),
+            event: get_optional_argument(sub_matches, "event"),
+            // An event from the environment only picks the Azure CLI profile.
+            only_event: sub_matches.occurrences_of("event") > 0,
+            backend: get_single_argument(sub_matches, "backend"),
+            azure_profile: get_optional_argument(sub_matches, "azure_profile"),
+            shared_azure_config: sub_matches.is_present("shared_azure_config"),
+            yes: sub_matches.is_present("yes"),
+            dry_run: sub_matches.is_present("dry_run"),
+            older_than,
+        };
+
+        exit_on_error(gc_config.execute());
+    }
+
+    if let Some(sub_matches) = matches.subcommand_matches("plan") {
+        let plan_config = PlanCommand {
+            config_path: get_single_argument(sub_matches, "config_file"),
+            subscription: sub_matches
+                .value_of("subscription")
+                .unwrap_or_default()
+                .to_string(),
+            event: Some(get_single_argument(sub_matches, "event")),
+            session_names: get_session_names(sub_matches),
+            location: get_optional_argument(sub_matches, "location"),
+            profile: get_optional_argument(sub_matches, "profile"),
+        };
+
+        exit_on_error(plan_config.execute());
+    }
+
+    if let Some(sub_matches) = matches.subcommand_matches("status") {
+        let status_config = StatusCommand {
+            config_path: get_single_argument(sub_matches, "config_file"),
+            subscription: sub_matches
+                .value_of("subscription")
+                .unwrap_or_default()
+                .to_string(),
+            event: get_optional_argument(sub_matches, "event"),
+            session_names: get_session_names(sub_matches),
+            profile: get_optional_argument(sub_matches, "profile"),
+            azure_profile: get_optional_argument(sub_matches, "azure_profile"),
+            shared_azure_config: sub_matches.is_present("shared_azure_config"),
+            json: sub_matches.is_present("json"),
+        };
+
+        exit_on_error(status_config.execute());
+    }
+
+    if let Some(sub_matches) = matches.subcommand_matches("validate") {
+        let validate_config = ValidateCommand::new(get_single_argument(sub_matches, "config_file"));
+        exit_on_error(validate_config.execute());
+    }
+
+    if let Some(sub_matches) = matches.subcommand_matches("migrate") {
+        let migrate_config = MigrateCommand::new(get_single_argument(sub_matches, "CONFIG_FILE"));
+        exit_on_error(migrate_config.execute());
+    }
+
+    if matches.subcommand_matches("schema").is_some() {
+        exit_on_error(SchemaCommand::default().execute());
+    }
+
     // if let Some(sub_matches) = matches.subcommand_matches("down") {
     // }
 
@@ -82,6 +219,13 @@ fn main() {
     }
 }
 
+fn exit_on_error<E: std::fmt::Display>(result: Result<(), E>) {
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
 fn get_single_argument(matches: &clap::ArgMatches, arg: &str) -> String {
     match matches.value_of(arg) {
         Some(s) => s.to_string(),
@@ -89,6 +233,28 @@ fn get_single_argument(matches: &clap::ArgMatches, arg: &str) -> String {
     }
 }
 
+fn get_optional_argument(matches: &clap::ArgMatches, arg: &str) -> Option<String> {
+    matches.value_of(arg).map(|s| s.to_string())
+}
+
+fn get_verification(matches: &clap::ArgMatches) -> Verification {
+    Verification {
+        sha256: get_optional_argument(matches, "config_sha256"),
+        public_key: get_optional_argument(matches, "config_public_key"),
+        allow_unsigned: matches.is_present("allow_unsigned"),
+    }
+}
+
+fn get_credential(matches: &clap::ArgMatches) -> CredentialOptions {
+    CredentialOptions {
+        client_id: get_optional_argument(matches, "client_id"),
+        tenant: get_optional_argument(matches, "tenant"),
+        client_secret: get_optional_argument(matches, "client_secret"),
+        certificate_path: get_optional_argument(matches, "client_certificate"),
+        managed_identity: matches.is_present("managed_identity"),
+    }
+}
+
 fn get_vec_argument(matches: &clap::ArgMatches, arg: &str) -> Vec<String> {
     matches
         .values_of(arg)
@@ -97,6 +263,23 @@ fn get_vec_argument(matches: &clap::ArgMatches, arg: &str) -> Vec<String> {
         .collect()
 }
 
+fn get_session_names(matches: &clap::ArgMatches) -> Option<Vec<String>> {
+    let mut session_names = None;
+    if matches.is_present("learning_path") {
+        let learning_paths = get_vec_argument(matches, "learning_path");
+        let mut sessions = Vec::new();
+        for l in learning_paths {
+            sessions.extend(map_learning_path_to_session_name(&l));
+        }
+        session_names = Some(sessions);
+    };
+
+    if matches.is_present("session_name") {
+        session_names = Some(get_vec_argument(matches, "session_name"));
+    };
+    session_names
+}
+
 fn map_learning_path_to_session_name(learning_path: &str) -> Vec<String> {
     let session_numbers = vec!["10", "20", "30", "40", "50"];
     session_numbers
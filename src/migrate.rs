@@ -0,0 +1,97 @@
+use crate::config::migration::{upgrade, CONFIG_VERSION};
+use crate::config::{read_config, ConfigError};
+use custom_error::custom_error;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+custom_error! {
+    pub DemoMigrateError
+    ConfigFailure{source: ConfigError} = "{source}",
+    RemoteConfig{path: String} = "{path} is not a local file.  Use demo fetch to get a local copy to migrate.",
+    BackupFailure{path: String, source: std::io::Error} = "Failed to back up the configuration file to {path}: {source}",
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MigrateCommand {
+    pub config_path: String,
+}
+
+impl MigrateCommand {
+    pub fn new(config_path: String) -> Self {
+        MigrateCommand { config_path }
+    }
+
+    // Includes are kept as they are, each included file can be migrated on its own.
+    pub fn execute(&self) -> Result<(), DemoMigrateError> {
+        if Url::parse(&self.config_path).is_ok() {
+            return Err(DemoMigrateError::RemoteConfig {
+                path: self.config_path.clone(),
+            });
+        }
+
+        let tour_config = read_config(&self.config_path)?;
+        let version = tour_config.version();
+        if version == CONFIG_VERSION {
+            println!(
+                "{} is already a version {} configuration file.",
+                self.config_path, CONFIG_VERSION
+            );
+            return Ok(());
+        }
+        let tour_config = upgrade(tour_config, &self.config_path)?;
+
+        let backup_path = backup_path(&self.config_path);
+        fs::copy(&self.config_path, &backup_path).map_err(|source| {
+            DemoMigrateError::BackupFailure {
+                path: backup_path.to_string_lossy().to_string(),
+                source,
+            }
+        })?;
+        tour_config.write(&PathBuf::from(&self.config_path));
+
+        println!(
+            "Migrated {} from version {} to version {}, the original is in {}.",
+            self.config_path,
+            version,
+            CONFIG_VERSION,
+            backup_path.display()
+        );
+        Ok(())
+    }
+}
+
+fn backup_path(config_path: &str) -> PathBuf {
+    PathBuf::from(format!("{}.bak", config_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::get_config;
+    use std::env;
+
+    #[test]
+    fn migrate_rewrites_the_config_and_keeps_a_backup() {
+        let path = env::temp_dir().join("demo-up-migrate.yml");
+        fs::copy("./test/artifacts/version_1_config.yml", &path).unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        MigrateCommand::new(path.clone()).execute().unwrap();
+        let migrated = read_config(&path).unwrap();
+        let original = fs::read_to_string(backup_path(&path)).unwrap();
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(backup_path(&path)).unwrap();
+
+        assert_eq!(migrated.version, Some(CONFIG_VERSION));
+        assert_eq!(
+            migrated,
+            get_config("./test/artifacts/version_1_config.yml").unwrap()
+        );
+        assert_eq!(
+            original,
+            fs::read_to_string("./test/artifacts/version_1_config.yml").unwrap()
+        );
+    }
+}
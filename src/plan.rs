@@ -0,0 +1,157 @@
+use crate::config::{get_profile_config, Command, ConfigError, Session};
+use crate::up::UpCommand;
+use custom_error::custom_error;
+use serde::{Deserialize, Serialize};
+
+custom_error! {
+    pub DemoPlanError
+    ConfigFailure{source: ConfigError} = "{source}",
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PlanCommand {
+    pub config_path: String,
+    pub subscription: String,
+    pub event: Option<String>,
+    pub session_names: Option<Vec<String>>,
+    pub location: Option<String>,
+    pub profile: Option<String>,
+}
+
+impl PlanCommand {
+    // Shows what `demo up` would set up with the same arguments, without touching Azure.
+    pub fn execute(&self) -> Result<(), DemoPlanError> {
+        let mut tour_config = get_profile_config(&self.config_path, self.profile.as_deref())?;
+
+        let session_names = match self.session_names {
+            Some(ref s) => s.clone(),
+            None => tour_config.sessions().iter().map(|s| s.name()).collect(),
+        };
+        let cli_args = UpCommand {
+            subscription: self.subscription.clone(),
+            event: self.event.clone(),
+            location: self.location.clone(),
+            session_names: Some(session_names),
+            ..Default::default()
+        };
+        tour_config.update(&cli_args);
+
+        if let Some(ref profile) = self.profile {
+            println!("Using the {} profile.\n", profile);
+        }
+        for session in tour_config.sessions() {
+            for line in plan_session(&session) {
+                println!("{}", line);
+            }
+            println!();
+        }
+        Ok(())
+    }
+}
+
+fn plan_session(session: &Session) -> Vec<String> {
+    let mut lines = vec![session.name()];
+    let row = |label: &str, value: &str| format!("\t\t{:<16}{}", label, value);
+    let unset = |value: &Option<String>| value.clone().unwrap_or_else(|| "(none)".to_string());
+
+    if let Some(ref slides) = session.slides {
+        lines.push(row("Slides", &unset(&slides.url)));
+    }
+    for video in session.videos.clone().unwrap_or_default() {
+        lines.push(row("Video", &unset(&video.url)));
+    }
+    for repo in session.git_repos.clone().unwrap_or_default() {
+        let mut value = unset(&repo.url);
+        if let Some(branch) = repo.branch {
+            value = format!("{} ({})", value, branch);
+        }
+        lines.push(row("Repository", &value));
+    }
+
+    let mut commands = session.commands();
+    commands.sort_by_key(|c| c.order());
+    for (i, command) in commands.iter().enumerate() {
+        lines.push(format!("\tCommand {}", command_name(command, i)));
+        lines.push(row("Subscription", &unset(&command.subscription)));
+        lines.push(row("Resource group", &unset(&command.resource_group)));
+        lines.push(row("Location", &unset(&command.location)));
+        if let Some(ref template) = command.template {
+            let source = template.path.clone().or_else(|| template.url.clone());
+            lines.push(row("Template", &unset(&source)));
+            let mut deployment = unset(&command.deployment_name);
+            if command.what_if() {
+                deployment.push_str(" (what-if)");
+            }
+            lines.push(row("Deployment", &deployment));
+            if let Some(ref file) = template.parameters_file {
+                lines.push(row("Parameters", file));
+            }
+            let parameters = template.parameters.clone().unwrap_or_default();
+            let mut names: Vec<&String> = parameters.keys().collect();
+            names.sort();
+            for name in names {
+                let value = serde_json::to_string(&parameters[name]).unwrap_or_default();
+                lines.push(row("Parameter", &format!("{} = {}", name, value)));
+            }
+        }
+        if let Some(ref cli) = command.cli {
+            let mut arguments = vec!["az".to_string()];
+            if cli.subcommand.is_some() {
+                arguments.extend(cli.subcommand());
+            }
+            arguments.extend(cli.parameters());
+            lines.push(row("Azure CLI", &arguments.join(" ")));
+        }
+    }
+    lines
+}
+
+fn command_name(command: &Command, index: usize) -> String {
+    match command.id {
+        Some(ref id) => id.to_string(),
+        None => (index + 1).to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{apply_profile, get_config};
+
+    #[test]
+    fn plan_shows_the_effective_commands() {
+        let mut tour_config = apply_profile(
+            get_config("./test/artifacts/profiles_config.yml").unwrap(),
+            "rehearsal",
+        )
+        .unwrap();
+        let cli_args = UpCommand {
+            event: Some("tour".to_string()),
+            session_names: Some(vec!["SRE10".to_string()]),
+            ..Default::default()
+        };
+        tour_config.update(&cli_args);
+
+        let lines = plan_session(&tour_config.sessions()[0]);
+
+        assert_eq!(
+            lines,
+            vec![
+                "SRE10",
+                "\tCommand webapp",
+                "\t\tSubscription    demo",
+                "\t\tResource group  SRE10-tour",
+                "\t\tLocation        eastus",
+                "\t\tTemplate        ./test/artifacts/demo_template.json",
+                "\t\tDeployment      SRE10-tour-webapp",
+                "\t\tParameter       sku = \"F1\"",
+                "\t\tParameter       webAppName = \"demoup\"",
+                "\tCommand list",
+                "\t\tSubscription    demo",
+                "\t\tResource group  SRE10-tour",
+                "\t\tLocation        eastus",
+                "\t\tAzure CLI       az webapp list",
+            ]
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use crate::config::TourConfig;
+use custom_error::custom_error;
+use schemars::schema::RootSchema;
+use schemars::schema_for;
+
+custom_error! {
+    pub DemoSchemaError
+    JsonError{source: serde_json::Error} = "Failed to write the schema as JSON.",
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SchemaCommand {}
+
+impl SchemaCommand {
+    pub fn execute(&self) -> Result<(), DemoSchemaError> {
+        println!("{}", serde_json::to_string_pretty(&schema())?);
+        Ok(())
+    }
+}
+
+pub fn schema() -> RootSchema {
+    let mut schema = schema_for!(TourConfig);
+    if let Some(ref mut metadata) = schema.schema.metadata {
+        metadata.title = Some("demo.yml".to_string());
+        metadata.description =
+            Some("The configuration for the sessions of an event, used by demo up.".to_string());
+    }
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn schema_describes_the_config_types() {
+        let schema = serde_json::to_value(schema()).unwrap();
+        let definitions = &schema["definitions"];
+
+        assert_eq!(schema["additionalProperties"], Value::Bool(false));
+        assert!(schema["required"].is_null());
+        assert!(schema["properties"]["sessions"]["description"].is_string());
+        for definition in &[
+            "Session",
+            "Command",
+            "CliCommand",
+            "ArmTemplate",
+            "FileDownload",
+        ] {
+            assert_eq!(
+                definitions[definition]["additionalProperties"],
+                Value::Bool(false)
+            );
+        }
+        assert!(definitions["Session"]["properties"]["git_repos"].is_object());
+    }
+
+    #[test]
+    fn locations_suggest_azure_regions() {
+        let schema = serde_json::to_value(schema()).unwrap();
+        let location = &schema["definitions"]["Command"]["properties"]["location"];
+
+        let regions = location["examples"].as_array().unwrap();
+        assert!(regions.contains(&Value::from("westus2")));
+        assert_eq!(location["type"], "string");
+        assert!(location["anyOf"].is_null());
+    }
+}
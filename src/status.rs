@@ -0,0 +1,400 @@
+use crate::config::{get_profile_config, Command, ConfigError, Session};
+use crate::tasks::az_cli::{get_account_info, show_deployment, show_resource_group, AzCliError};
+use crate::tasks::git::git_status;
+use crate::tasks::{get_filename, use_isolated_azure_config};
+use crate::up::UpCommand;
+use custom_error::custom_error;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::env;
+use std::path::Path;
+
+custom_error! {
+    pub DemoStatusError
+    Failed = "Failed to determine the status of the demo environment.",
+    ConfigFailure{source: ConfigError} = "{source}",
+    JsonError{source: serde_json::Error} = "Failed to write the status as JSON.",
+    AzureConfigFailure{source: AzCliError} = "{source}",
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct StatusCommand {
+    pub config_path: String,
+    pub subscription: String,
+    pub event: Option<String>,
+    pub session_names: Option<Vec<String>>,
+    pub profile: Option<String>,
+    pub azure_profile: Option<String>,
+    pub shared_azure_config: bool,
+    pub json: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SessionStatus {
+    pub name: String,
+    pub local: LocalStatus,
+    pub cloud: Option<Vec<CommandStatus>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct LocalStatus {
+    pub directory: bool,
+    pub slides: Option<FileStatus>,
+    pub videos: Vec<FileStatus>,
+    pub repos: Vec<RepoStatus>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct FileStatus {
+    pub name: String,
+    pub exists: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct RepoStatus {
+    pub name: String,
+    pub exists: bool,
+    pub branch: Option<String>,
+    pub dirty: Option<bool>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CommandStatus {
+    pub resource_group: String,
+    pub resource_group_exists: bool,
+    pub provisioning_state: Option<String>,
+    pub deployment_name: Option<String>,
+    pub deployment_state: Option<String>,
+    pub outputs: BTreeMap<String, Value>,
+}
+
+impl StatusCommand {
+    fn azure_profile(&self) -> Option<String> {
+        self.azure_profile.clone().or_else(|| self.event.clone())
+    }
+
+    pub fn execute(&self) -> Result<(), DemoStatusError> {
+        let mut tour_config = get_profile_config(&self.config_path, self.profile.as_deref())?;
+
+        // Resource group and deployment names are derived the same way `demo up` derives them.
+        let session_names = match self.session_names {
+            Some(ref s) => s.clone(),
+            None => tour_config.sessions().iter().map(|s| s.name()).collect(),
+        };
+        let cli_args = UpCommand {
+            subscription: self.subscription.clone(),
+            event: self.event.clone(),
+            session_names: Some(session_names),
+            ..Default::default()
+        };
+        tour_config.update(&cli_args);
+
+        if !self.shared_azure_config {
+            if let Some(profile) = self.azure_profile() {
+                use_isolated_azure_config(&profile)?;
+            }
+        }
+        // The status never logs in, so without a login only the local state is reported.
+        let logged_in = get_account_info().is_ok();
+        if !logged_in && !self.json {
+            println!("The Azure CLI is not logged in, skipping the cloud state.\n");
+        }
+
+        let directory = env::current_dir().unwrap();
+        let mut statuses = Vec::new();
+        for session in tour_config.sessions() {
+            let cloud = if logged_in {
+                Some(cloud_status(&session))
+            } else {
+                None
+            };
+            statuses.push(SessionStatus {
+                name: session.name(),
+                local: local_status(&session, &directory.join(session.name())),
+                cloud,
+            });
+        }
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&statuses)?);
+        } else {
+            for status in &statuses {
+                print_status(status);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn local_status(session: &Session, session_directory: &Path) -> LocalStatus {
+    let slides = session.slides.as_ref().and_then(|s| {
+        s.url.as_ref().map(|url| {
+            let default_filename = s.file_name.clone().unwrap_or_default();
+            file_status(
+                &session_directory.join("slides"),
+                get_filename(url, &default_filename),
+            )
+        })
+    });
+
+    let videos = session
+        .videos
+        .clone()
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|v| v.url.as_ref())
+        .map(|url| file_status(&session_directory.join("videos"), get_filename(url, "")))
+        .collect();
+
+    let repos = session
+        .git_repos
+        .clone()
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|r| r.url.as_ref())
+        .map(|url| {
+            let name = get_filename(url, "");
+            let state = git_status(&session_directory.join("src").join(&name));
+            RepoStatus {
+                name,
+                exists: state.is_some(),
+                branch: state.as_ref().and_then(|s| s.branch.clone()),
+                dirty: state.map(|s| s.dirty),
+            }
+        })
+        .collect();
+
+    LocalStatus {
+        directory: session_directory.is_dir(),
+        slides,
+        videos,
+        repos,
+    }
+}
+
+fn file_status(directory: &Path, name: String) -> FileStatus {
+    FileStatus {
+        exists: directory.join(&name).is_file(),
+        name,
+    }
+}
+
+fn cloud_status(session: &Session) -> Vec<CommandStatus> {
+    let mut commands = session.commands();
+    commands.sort_by_key(|c| c.order());
+
+    commands
+        .iter()
+        .map(|command| {
+            let resource_group = command.resource_group.clone().unwrap_or_default();
+            let subscription = command.subscription.clone().unwrap_or_default();
+            let group = show_resource_group(&resource_group, &subscription).unwrap_or(None);
+            let deployment = match command.template {
+                Some(_) if group.is_some() => {
+                    show_deployment(&resource_group, &command.deployment_name(), &subscription)
+                        .unwrap_or(None)
+                }
+                _ => None,
+            };
+            command_status(command, group, deployment)
+        })
+        .collect()
+}
+
+fn command_status(
+    command: &Command,
+    group: Option<Value>,
+    deployment: Option<Value>,
+) -> CommandStatus {
+    let provisioning_state = |v: &Value| {
+        v["properties"]["provisioningState"]
+            .as_str()
+            .map(|s| s.to_string())
+    };
+
+    // Deployment outputs are `{"name": {"type": ..., "value": ...}}`, only the values are kept.
+    let mut outputs = BTreeMap::new();
+    if let Some(Value::Object(o)) = deployment.as_ref().map(|d| &d["properties"]["outputs"]) {
+        for (name, output) in o {
+            outputs.insert(name.to_string(), output["value"].clone());
+        }
+    }
+
+    CommandStatus {
+        resource_group: command.resource_group.clone().unwrap_or_default(),
+        resource_group_exists: group.is_some(),
+        provisioning_state: group.as_ref().and_then(provisioning_state),
+        deployment_name: command
+            .template
+            .as_ref()
+            .and(command.deployment_name.clone()),
+        deployment_state: deployment.as_ref().and_then(provisioning_state),
+        outputs,
+    }
+}
+
+fn present(exists: bool) -> &'static str {
+    if exists {
+        "present"
+    } else {
+        "missing"
+    }
+}
+
+fn print_status(status: &SessionStatus) {
+    println!("{}", status.name);
+    println!("\tLocal");
+    println!(
+        "\t\t{:<16}{:<40}{}",
+        "Directory",
+        status.name,
+        present(status.local.directory)
+    );
+    if let Some(ref slides) = status.local.slides {
+        println!(
+            "\t\t{:<16}{:<40}{}",
+            "Slides",
+            slides.name,
+            present(slides.exists)
+        );
+    }
+    for video in &status.local.videos {
+        println!(
+            "\t\t{:<16}{:<40}{}",
+            "Video",
+            video.name,
+            present(video.exists)
+        );
+    }
+    for repo in &status.local.repos {
+        let mut state = present(repo.exists).to_string();
+        if let Some(ref branch) = repo.branch {
+            state = format!("{} ({}", state, branch);
+            if repo.dirty == Some(true) {
+                state.push_str(", dirty");
+            }
+            state.push(')');
+        }
+        println!("\t\t{:<16}{:<40}{}", "Repository", repo.name, state);
+    }
+
+    if let Some(ref cloud) = status.cloud {
+        println!("\tCloud");
+        for command in cloud {
+            let group_state = match command.provisioning_state {
+                Some(ref s) => s.to_string(),
+                None => present(command.resource_group_exists).to_string(),
+            };
+            println!(
+                "\t\t{:<16}{:<40}{}",
+                "Resource group", command.resource_group, group_state
+            );
+            if let Some(ref name) = command.deployment_name {
+                let deployment_state = command
+                    .deployment_state
+                    .clone()
+                    .unwrap_or_else(|| "missing".to_string());
+                println!("\t\t{:<16}{:<40}{}", "Deployment", name, deployment_state);
+            }
+            for (name, value) in &command.outputs {
+                println!("\t\t{:<16}{:<40}{}", "Output", name, value);
+            }
+        }
+    }
+    println!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ArmTemplate, FileDownload, GitRepo};
+    use serde_json::json;
+    use std::fs;
+
+    #[test]
+    fn local_status_reports_missing_files() {
+        let directory = env::temp_dir().join("demo-up-status-SRE10");
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(directory.join("videos")).unwrap();
+        fs::write(directory.join("videos").join("demo.mp4"), "").unwrap();
+
+        let slides = FileDownload {
+            url: Some("https://example.com/slides/SRE10.pptx".to_string()),
+            ..Default::default()
+        };
+        let video = FileDownload {
+            url: Some("https://example.com/videos/demo.mp4".to_string()),
+            ..Default::default()
+        };
+        let session = Session {
+            name: Some("SRE10".to_string()),
+            slides: Some(slides),
+            videos: Some(vec![video]),
+            git_repos: Some(vec![GitRepo::new("https://github.com/example/sre10")]),
+            ..Default::default()
+        };
+
+        let status = local_status(&session, &directory);
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert!(status.directory);
+        assert_eq!(
+            status.slides,
+            Some(FileStatus {
+                name: "SRE10.pptx".to_string(),
+                exists: false
+            })
+        );
+        assert!(status.videos[0].exists);
+        assert_eq!(
+            status.repos[0],
+            RepoStatus {
+                name: "sre10".to_string(),
+                exists: false,
+                branch: None,
+                dirty: None
+            }
+        );
+    }
+
+    #[test]
+    fn command_status_reads_deployment_outputs() {
+        let command = Command {
+            resource_group: Some("SRE10-tour".to_string()),
+            deployment_name: Some("SRE10-tour-1".to_string()),
+            template: Some(ArmTemplate::default()),
+            ..Default::default()
+        };
+        let group = json!({"properties": {"provisioningState": "Succeeded"}});
+        let deployment = json!({"properties": {
+            "provisioningState": "Failed",
+            "outputs": {"webAppUrl": {"type": "String", "value": "https://sre10.azurewebsites.net"}}
+        }});
+
+        let status = command_status(&command, Some(group), Some(deployment));
+
+        assert!(status.resource_group_exists);
+        assert_eq!(status.provisioning_state, Some("Succeeded".to_string()));
+        assert_eq!(status.deployment_name, Some("SRE10-tour-1".to_string()));
+        assert_eq!(status.deployment_state, Some("Failed".to_string()));
+        assert_eq!(
+            status.outputs["webAppUrl"],
+            json!("https://sre10.azurewebsites.net")
+        );
+    }
+
+    #[test]
+    fn command_status_without_a_resource_group() {
+        let command = Command {
+            resource_group: Some("SRE10-tour".to_string()),
+            ..Default::default()
+        };
+
+        let status = command_status(&command, None, None);
+
+        assert!(!status.resource_group_exists);
+        assert_eq!(status.deployment_name, None);
+        assert!(status.outputs.is_empty());
+    }
+}
@@ -0,0 +1,541 @@
+use super::az_cli::AzAccountInfo;
+use super::backend::{keep_created_tag, CloudBackend, CloudBackendError, ResourceGroup};
+use config::{read, Command};
+use reqwest::header::HeaderMap;
+use reqwest::{Client, RequestBuilder};
+use serde_json::{json, Value};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const RESOURCES_API_VERSION: &str = "2019-05-10";
+const SUBSCRIPTIONS_API_VERSION: &str = "2016-06-01";
+const POLLING_INTERVAL_SECONDS: u64 = 5;
+const DEPLOYMENT_TIMEOUT_MINUTES: u64 = 120;
+const ASYNC_OPERATION_HEADER: &str = "azure-asyncoperation";
+const RETRY_AFTER_HEADER: &str = "retry-after";
+
+// Talks to the Azure Resource Manager REST API directly with a bearer token, so the Azure CLI
+// is not needed to provision resource groups and template deployments.
+pub struct ArmRestBackend {
+    client: Client,
+    endpoint: String,
+    token: String,
+    subscription_id: String,
+    polling_interval: Duration,
+    deployment_timeout: Duration,
+}
+
+impl ArmRestBackend {
+    pub fn new(endpoint: &str, token: &str, subscription_id: &str) -> Self {
+        ArmRestBackend {
+            client: Client::new(),
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+            subscription_id: subscription_id.to_string(),
+            polling_interval: Duration::from_secs(POLLING_INTERVAL_SECONDS),
+            deployment_timeout: Duration::from_secs(DEPLOYMENT_TIMEOUT_MINUTES * 60),
+        }
+    }
+
+    fn subscription_url(&self) -> String {
+        format!("{}/subscriptions/{}", self.endpoint, self.subscription_id)
+    }
+
+    fn resource_group_url(&self, name: &str) -> String {
+        format!(
+            "{}/resourcegroups/{}?api-version={}",
+            self.subscription_url(),
+            name,
+            RESOURCES_API_VERSION
+        )
+    }
+
+    fn deployment_url(&self, resource_group: &str, name: &str) -> String {
+        format!(
+            "{}/resourcegroups/{}/providers/Microsoft.Resources/deployments/{}?api-version={}",
+            self.subscription_url(),
+            resource_group,
+            name,
+            RESOURCES_API_VERSION
+        )
+    }
+
+    fn send(&self, request: RequestBuilder) -> Result<Value, CloudBackendError> {
+        self.send_with_headers(request).map(|(body, _)| body)
+    }
+
+    fn send_with_headers(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<(Value, HeaderMap), CloudBackendError> {
+        let mut response = request.bearer_auth(&self.token).send()?;
+        let body = response.text()?;
+
+        if !response.status().is_success() {
+            return Err(CloudBackendError::UnexpectedResponse {
+                status: response.status().as_u16(),
+                body,
+            });
+        }
+
+        let headers = response.headers().clone();
+        if body.trim().is_empty() {
+            Ok((Value::Null, headers))
+        } else {
+            Ok((serde_json::from_str(&body)?, headers))
+        }
+    }
+
+    // Deployments are long running, so poll until they reach a terminal state.  Azure Resource
+    // Manager says where (the Azure-AsyncOperation header) and how often (Retry-After) to poll;
+    // without them the deployment itself is polled.  A deployment that never finishes is an
+    // error once the timeout has passed.
+    fn wait_for_deployment(
+        &self,
+        url: &str,
+        name: &str,
+        headers: &HeaderMap,
+    ) -> Result<(), CloudBackendError> {
+        let deadline = Instant::now() + self.deployment_timeout;
+        let status_url = header(headers, ASYNC_OPERATION_HEADER).unwrap_or_else(|| url.to_string());
+        let mut interval = self.retry_after(headers);
+        loop {
+            if Instant::now() + interval > deadline {
+                return Err(CloudBackendError::DeploymentTimedOut {
+                    name: name.to_string(),
+                    minutes: self.deployment_timeout.as_secs() / 60,
+                });
+            }
+            thread::sleep(interval);
+
+            let (status, headers) = self.send_with_headers(self.client.get(&status_url))?;
+            let state = status["status"]
+                .as_str()
+                .or_else(|| status["properties"]["provisioningState"].as_str());
+            match state {
+                Some("Succeeded") => return Ok(()),
+                Some(state) if state == "Failed" || state == "Canceled" => {
+                    return Err(CloudBackendError::DeploymentFailed {
+                        name: name.to_string(),
+                        state: state.to_string(),
+                    });
+                }
+                _ => interval = self.retry_after(&headers),
+            }
+        }
+    }
+
+    // Retry-After is a number of seconds here, the HTTP date form is not used by Azure Resource
+    // Manager.
+    fn retry_after(&self, headers: &HeaderMap) -> Duration {
+        header(headers, RETRY_AFTER_HEADER)
+            .and_then(|s| s.trim().parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(self.polling_interval)
+    }
+}
+
+fn header(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string())
+}
+
+fn deployment_properties(command: &Command) -> Result<Value, CloudBackendError> {
+    let template = command.template();
+    let mut properties = json!({ "mode": "Incremental" });
+
+    if template.path.is_some() {
+        let content = read(Path::new(&template.path()))?;
+        properties["template"] = serde_json::from_str(&content)?;
+    } else if template.url.is_some() {
+        properties["templateLink"] = json!({ "uri": template.url() });
+    } else {
+        return Err(CloudBackendError::MissingTemplate);
+    }
+
+    if template.has_parameters() {
+        properties["parameters"] = template.parameters()?["parameters"].clone();
+    }
+
+    Ok(properties)
+}
+
+impl CloudBackend for ArmRestBackend {
+    fn ensure_resource_group(&self, command: &Command) -> Result<(), CloudBackendError> {
+        let local_command = command.clone();
+        let rg = local_command.resource_group.unwrap();
+        let location =
+            local_command
+                .location
+                .ok_or_else(|| CloudBackendError::MissingLocation {
+                    resource_group: rg.to_string(),
+                })?;
+        let url = self.resource_group_url(&rg);
+        let mut tags = command.tags();
+        match self.send(self.client.get(&url)) {
+            Ok(existing) => keep_created_tag(&mut tags, &existing),
+            Err(CloudBackendError::UnexpectedResponse { status: 404, .. }) => {}
+            Err(e) => return Err(e),
+        }
+
+        let body = json!({ "location": location, "tags": tags });
+        self.send(self.client.put(&url).json(&body))?;
+        Ok(())
+    }
+
+    fn deploy_template(&self, command: &Command) -> Result<(), CloudBackendError> {
+        self.ensure_resource_group(command)?;
+
+        let name = command.deployment_name();
+        let body = json!({ "properties": deployment_properties(command)? });
+
+        let url = self.deployment_url(&command.clone().resource_group.unwrap(), &name);
+        let (_, headers) = self.send_with_headers(self.client.put(&url).json(&body))?;
+        self.wait_for_deployment(&url, &name, &headers)
+    }
+
+    fn delete_resource_group(&self, name: &str) -> Result<(), CloudBackendError> {
+        let url = self.resource_group_url(name);
+        self.send(self.client.delete(&url))?;
+        Ok(())
+    }
+
+    fn list_resource_groups(&self, tag: &str) -> Result<Vec<ResourceGroup>, CloudBackendError> {
+        let mut url = format!(
+            "{}/resourcegroups?api-version={}&$filter=tagName eq '{}'",
+            self.subscription_url(),
+            RESOURCES_API_VERSION,
+            tag
+        );
+        let mut groups = Vec::new();
+        // Large subscriptions are listed a page at a time.
+        loop {
+            let page = self.send(self.client.get(&url))?;
+            let values: Vec<ResourceGroup> = serde_json::from_value(page["value"].clone())?;
+            groups.extend(values);
+            match page["nextLink"].as_str() {
+                Some(next) => url = next.to_string(),
+                None => return Ok(groups),
+            }
+        }
+    }
+
+    fn show_account(&self) -> Result<AzAccountInfo, CloudBackendError> {
+        let url = format!(
+            "{}?api-version={}",
+            self.subscription_url(),
+            SUBSCRIPTIONS_API_VERSION
+        );
+        let subscription = self.send(self.client.get(&url))?;
+
+        Ok(AzAccountInfo {
+            subscription_name: subscription["displayName"].as_str().map(|s| s.to_string()),
+            subscription_id: subscription["subscriptionId"]
+                .as_str()
+                .map(|s| s.to_string()),
+            tenant_id: subscription["tenantId"].as_str().map(|s| s.to_string()),
+            tenant_domains: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::ArmTemplate;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    const SUBSCRIPTION_ID: &str = "00000000-0000-0000-0000-000000000000";
+
+    // A minimal HTTP server answering each connection with the next canned response and
+    // recording the request line, authorization header and body it received.  `{endpoint}` in a
+    // response is replaced with the address of the server.
+    fn mock_arm(responses: Vec<(u16, &'static str)>) -> (ArmRestBackend, JoinHandle<Vec<String>>) {
+        mock_arm_with_headers(responses.into_iter().map(|(s, b)| (s, "", b)).collect())
+    }
+
+    // Each response can also have extra headers, one per line.
+    fn mock_arm_with_headers(
+        responses: Vec<(u16, &'static str, &'static str)>,
+    ) -> (ArmRestBackend, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let address = endpoint.clone();
+
+        let server = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for (status, headers, body) in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut authorization = String::new();
+                let mut content_length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    let header = header.trim().to_string();
+                    if header.is_empty() {
+                        break;
+                    }
+                    let lower = header.to_lowercase();
+                    if lower.starts_with("authorization:") {
+                        authorization = header["authorization:".len()..].trim().to_string();
+                    } else if lower.starts_with("content-length:") {
+                        content_length = header["content-length:".len()..].trim().parse().unwrap();
+                    }
+                }
+                let mut request_body = vec![0; content_length];
+                reader.read_exact(&mut request_body).unwrap();
+                requests.push(format!(
+                    "{} {} {}",
+                    request_line.trim(),
+                    authorization,
+                    String::from_utf8(request_body).unwrap()
+                ));
+
+                let body = body.replace("{endpoint}", &address);
+                let headers: String = headers
+                    .replace("{endpoint}", &address)
+                    .lines()
+                    .map(|h| format!("{}\r\n", h))
+                    .collect();
+                let response = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    headers,
+                    body
+                );
+                reader.get_mut().write_all(response.as_bytes()).unwrap();
+            }
+            requests
+        });
+
+        let mut backend = ArmRestBackend::new(&endpoint, "token", SUBSCRIPTION_ID);
+        backend.polling_interval = Duration::from_millis(0);
+        (backend, server)
+    }
+
+    fn template_command() -> Command {
+        let mut parameters = std::collections::HashMap::new();
+        parameters.insert(
+            "webAppName".to_string(),
+            serde_yaml::Value::String("demoup".to_string()),
+        );
+        let template = ArmTemplate {
+            path: Some("./test/artifacts/demo_template.json".to_string()),
+            parameters: Some(parameters),
+            ..Default::default()
+        };
+
+        let mut tags = crate::config::Tags::new();
+        tags.insert("demo-up-event".to_string(), "tour".to_string());
+        Command {
+            resource_group: Some("SRE10-tour".to_string()),
+            location: Some("westus2".to_string()),
+            deployment_name: Some("SRE10-tour-1".to_string()),
+            tags: Some(tags),
+            template: Some(template),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn ensure_resource_group_puts_the_group() {
+        let (backend, server) = mock_arm(vec![
+            (404, r#"{"error": {"code": "ResourceGroupNotFound"}}"#),
+            (201, "{}"),
+        ]);
+
+        backend.ensure_resource_group(&template_command()).unwrap();
+        let requests = server.join().unwrap();
+
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].starts_with("GET "));
+        assert!(requests[1].starts_with(&format!(
+            "PUT /subscriptions/{}/resourcegroups/SRE10-tour?api-version={}",
+            SUBSCRIPTION_ID, RESOURCES_API_VERSION
+        )));
+        assert!(requests[1].contains("Bearer token"));
+        assert!(requests[1].contains("\"location\":\"westus2\""));
+        assert!(requests[1].contains("\"tags\":{\"demo-up-event\":\"tour\"}"));
+    }
+
+    #[test]
+    fn existing_resource_groups_keep_their_created_tag() {
+        let (backend, server) = mock_arm(vec![
+            (
+                200,
+                r#"{"name": "SRE10-tour", "tags": {"demo-up-created": "2019-11-04T09:30:00Z"}}"#,
+            ),
+            (200, "{}"),
+        ]);
+        let mut command = template_command();
+        command.tags.as_mut().unwrap().insert(
+            "demo-up-created".to_string(),
+            "2019-11-05T10:00:00Z".to_string(),
+        );
+
+        backend.ensure_resource_group(&command).unwrap();
+        let requests = server.join().unwrap();
+
+        assert!(requests[1].contains("\"demo-up-created\":\"2019-11-04T09:30:00Z\""));
+    }
+
+    #[test]
+    fn deploy_template_waits_for_the_deployment() {
+        let (backend, server) = mock_arm(vec![
+            (404, r#"{"error": {"code": "ResourceGroupNotFound"}}"#),
+            (200, "{}"),
+            (201, r#"{"properties": {"provisioningState": "Accepted"}}"#),
+            (200, r#"{"properties": {"provisioningState": "Running"}}"#),
+            (200, r#"{"properties": {"provisioningState": "Succeeded"}}"#),
+        ]);
+
+        backend.deploy_template(&template_command()).unwrap();
+        let requests = server.join().unwrap();
+
+        assert_eq!(requests.len(), 5);
+        assert!(requests[2].starts_with(
+            "PUT /subscriptions/00000000-0000-0000-0000-000000000000/resourcegroups/SRE10-tour/providers/Microsoft.Resources/deployments/SRE10-tour-1"
+        ));
+        assert!(requests[2].contains("\"mode\":\"Incremental\""));
+        assert!(requests[2].contains("\"webAppName\":{\"value\":\"demoup\"}"));
+        assert!(requests[2].contains("\"contentVersion\":\"1.0.0.0\""));
+        assert!(requests[4].starts_with("GET "));
+    }
+
+    #[test]
+    fn failed_deployment_is_an_error() {
+        let (backend, server) = mock_arm(vec![
+            (404, r#"{"error": {"code": "ResourceGroupNotFound"}}"#),
+            (200, "{}"),
+            (201, r#"{"properties": {"provisioningState": "Failed"}}"#),
+            (200, r#"{"properties": {"provisioningState": "Failed"}}"#),
+        ]);
+
+        let result = backend.deploy_template(&template_command());
+        server.join().unwrap();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn deployments_are_polled_where_azure_says() {
+        let (backend, server) = mock_arm_with_headers(vec![
+            (404, "", "{}"),
+            (200, "", "{}"),
+            (
+                201,
+                "Azure-AsyncOperation: {endpoint}/operations/1\nRetry-After: 0",
+                r#"{"properties": {"provisioningState": "Accepted"}}"#,
+            ),
+            (200, "Retry-After: 0", r#"{"status": "Running"}"#),
+            (200, "", r#"{"status": "Succeeded"}"#),
+        ]);
+
+        backend.deploy_template(&template_command()).unwrap();
+        let requests = server.join().unwrap();
+
+        assert!(requests[3].starts_with("GET /operations/1 "));
+        assert!(requests[4].starts_with("GET /operations/1 "));
+    }
+
+    #[test]
+    fn deployments_that_do_not_finish_time_out() {
+        let (mut backend, server) = mock_arm(vec![
+            (404, r#"{"error": {"code": "ResourceGroupNotFound"}}"#),
+            (200, "{}"),
+            (201, r#"{"properties": {"provisioningState": "Accepted"}}"#),
+        ]);
+        backend.polling_interval = Duration::from_secs(1);
+        backend.deployment_timeout = Duration::from_millis(500);
+
+        let result = backend.deploy_template(&template_command());
+        server.join().unwrap();
+
+        match result {
+            Err(CloudBackendError::DeploymentTimedOut { name, .. }) => {
+                assert_eq!(name, "SRE10-tour-1")
+            }
+            r => panic!("Expected a timeout, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn retry_after_sets_the_polling_interval() {
+        let (backend, server) = mock_arm(vec![]);
+        server.join().unwrap();
+        let mut headers = HeaderMap::new();
+
+        assert_eq!(backend.retry_after(&headers), Duration::from_millis(0));
+        headers.insert("Retry-After", "7".parse().unwrap());
+        assert_eq!(backend.retry_after(&headers), Duration::from_secs(7));
+    }
+
+    #[test]
+    fn error_responses_are_reported() {
+        let (backend, server) =
+            mock_arm(vec![(403, r#"{"error": {"code": "AuthorizationFailed"}}"#)]);
+
+        let result = backend.delete_resource_group("SRE10-tour");
+        let requests = server.join().unwrap();
+
+        assert!(requests[0].starts_with("DELETE /subscriptions/"));
+        match result {
+            Err(CloudBackendError::UnexpectedResponse { status, body }) => {
+                assert_eq!(status, 403);
+                assert!(body.contains("AuthorizationFailed"));
+            }
+            _ => panic!("Expected an unexpected response error."),
+        }
+    }
+
+    #[test]
+    fn list_resource_groups_follows_next_links() {
+        let (backend, server) = mock_arm(vec![
+            (
+                200,
+                r#"{"value": [{"name": "SRE10-tour", "location": "westus2", "tags": {"demo-up-event": "tour"}}], "nextLink": "{endpoint}/subscriptions/00000000-0000-0000-0000-000000000000/resourcegroups?page=2"}"#,
+            ),
+            (
+                200,
+                r#"{"value": [{"name": "SRE20-tour", "location": "westus2", "tags": null}]}"#,
+            ),
+        ]);
+        let groups = backend.list_resource_groups("demo-up-event");
+        let requests = server.join().unwrap();
+
+        assert!(requests[0].contains("$filter=tagName%20eq%20'demo-up-event'"));
+        assert!(requests[1].contains("resourcegroups?page=2"));
+        let groups = groups.unwrap();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].tags()["demo-up-event"], "tour");
+        assert!(groups[1].tags().is_empty());
+    }
+
+    #[test]
+    fn show_account_reads_the_subscription() {
+        let (backend, server) = mock_arm(vec![(
+            200,
+            r#"{"subscriptionId": "00000000-0000-0000-0000-000000000000", "displayName": "Ignite the Tour", "tenantId": "tenant"}"#,
+        )]);
+
+        let account = backend.show_account().unwrap();
+        server.join().unwrap();
+
+        assert_eq!(
+            account.subscription_name,
+            Some("Ignite the Tour".to_string())
+        );
+        assert_eq!(account.subscription_id, Some(SUBSCRIPTION_ID.to_string()));
+        assert_eq!(account.tenant_id, Some("tenant".to_string()));
+    }
+}
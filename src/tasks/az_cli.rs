@@ -1,24 +1,76 @@
-use config::Command;
+use super::backend::{keep_created_tag, CloudBackend, CloudBackendError, ResourceGroup};
+use super::cassette::{
+    record, replay, replay_cassette, CassetteError, Interaction, RECORD_VARIABLE,
+};
+use super::credential::{Credential, CredentialError, CredentialOptions};
+use config::arm_template::ParametersFileError;
+use config::{Command, Tags};
 use custom_error::custom_error;
 use duct::cmd;
 use regex::Regex;
+use serde::Deserialize;
 use serde_json::Value;
+use std::collections::HashMap;
 use std::env;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
 use std::process::Output;
-use uuid::Uuid;
+use tempfile::{Builder, NamedTempFile};
 
 custom_error! {
     pub AzCliError
     Unknown = "unknown error",
     CliMissing = "Unable to find the Azure CLI.",
+    MissingConfigDirectory = "Unable to find a configuration directory for the Azure CLI profile.",
     InvalidJsonError{source: std::string::FromUtf8Error} = "Failed to convert the output.",
     RegexError{source: regex::Error} = "Regex problem.",
     JsonDeserializationError{source: serde_json::Error} = "JSON error",
     CommandFailure{source: std::io::Error} = "Unable to log in via the Azure CLI",
+    CliFailed{message: String} = "The Azure CLI failed: {message}",
     NotLoggedIn = "Az CLI is not authenticated.",
+    LoginFailed{message: String} = "Failed to log in to the Azure CLI: {message}",
+    SubscriptionNotFound{subscription: String} = "Unable to find the {subscription} subscription.",
     MissingTemplate = "No template available to deploy",
+    MissingLocation{resource_group: String} = "No location for the {resource_group} resource group.",
+    ParametersError{source: ParametersFileError} = "Failed to prepare the template parameters.",
+    CassetteFailure{source: CassetteError} = "{source}",
+    CredentialFailure{source: CredentialError} = "{source}",
+}
+
+const AZURE_CONFIG_DIR_VARIABLE: &str = "AZURE_CONFIG_DIR";
+// The Azure CLI exits with 3 when the resource it is asked to show does not exist.
+const NOT_FOUND_EXIT_CODE: i32 = 3;
+pub const PARAMETERS_FILE_PREFIX: &str = "demo-up-";
+
+// Points every Azure CLI invocation at a configuration directory dedicated to the profile, so
+// logins and default subscriptions for demo environments never touch the user's own.
+pub fn use_isolated_azure_config(profile: &str) -> Result<PathBuf, AzCliError> {
+    let directory = azure_config_directory(profile)?;
+    std::fs::create_dir_all(&directory)?;
+    env::set_var(AZURE_CONFIG_DIR_VARIABLE, &directory);
+    Ok(directory)
+}
+
+fn azure_config_directory(profile: &str) -> Result<PathBuf, AzCliError> {
+    let mut profile: String = profile
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "_-.".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    // Names made only of dots (like `..`) would point outside the profiles directory.
+    if profile.chars().all(|c| c == '.') {
+        profile = "_".repeat(profile.len().max(1));
+    }
+
+    match dirs::config_dir() {
+        Some(d) => Ok(d.join("demo-up").join("azure").join(profile)),
+        None => Err(AzCliError::MissingConfigDirectory),
+    }
 }
 
 fn get_az_cli_path() -> Result<PathBuf, AzCliError> {
@@ -31,9 +83,35 @@ fn get_az_cli_path() -> Result<PathBuf, AzCliError> {
 
 #[derive(Clone, Debug)]
 pub struct AzAccountInfo {
-    subscription_name: Option<String>,
-    subscription_id: Option<String>,
-    tenant_id: Option<String>,
+    pub subscription_name: Option<String>,
+    pub subscription_id: Option<String>,
+    pub tenant_id: Option<String>,
+    pub tenant_domains: Vec<String>,
+}
+
+impl AzAccountInfo {
+    // Subscriptions can be referred to either by name or by id.
+    pub fn is_subscription(&self, subscription: &str) -> bool {
+        let by_name = self.subscription_name.as_ref().map(|n| n == subscription);
+        let by_id = self
+            .subscription_id
+            .as_ref()
+            .map(|i| i.eq_ignore_ascii_case(subscription));
+        by_name == Some(true) || by_id == Some(true)
+    }
+
+    // Tenants can be referred to either by id or by one of their domains.
+    pub fn is_tenant(&self, tenant: &str) -> bool {
+        let by_id = self
+            .tenant_id
+            .as_ref()
+            .map(|t| t.eq_ignore_ascii_case(tenant));
+        let by_domain = self
+            .tenant_domains
+            .iter()
+            .any(|d| d.eq_ignore_ascii_case(tenant));
+        by_id == Some(true) || by_domain
+    }
 }
 
 impl Default for AzAccountInfo {
@@ -42,75 +120,229 @@ impl Default for AzAccountInfo {
             subscription_name: None,
             subscription_id: None,
             tenant_id: None,
+            tenant_domains: Vec::new(),
         }
     }
 }
 
-pub fn set_azure_environment(subscription: &str) -> Result<(), AzCliError> {
+#[derive(Deserialize)]
+struct AccountShow {
+    id: String,
+    name: String,
+    #[serde(rename = "tenantId")]
+    tenant_id: String,
+    #[serde(rename = "tenantDefaultDomain")]
+    tenant_default_domain: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TenantList {
+    #[serde(rename = "tenantId")]
+    tenant_id: String,
+    #[serde(default)]
+    domains: Vec<String>,
+}
+
+pub fn set_azure_environment(
+    subscription: &str,
+    credential: &CredentialOptions,
+) -> Result<(), AzCliError> {
+    let tenant = credential.tenant.as_deref();
     println!(
         "Checking to see if the Azure CLI is authenticated and which subscription is default."
     );
-    let account = match get_account_info() {
+    let mut account = match get_account_info() {
         Ok(a) => a,
         Err(_) => {
-            login()?;
+            login(&credential.credential()?)?;
             println!("Checking for the default subscription.");
             get_account_info()?
         }
     };
 
-    if let Some(account_subscription) = account.subscription_name {
-        println!("The default subscription is {}", &account_subscription);
-
-        if !subscription.is_empty() {
-            if account_subscription.trim_matches('"') == subscription {
-                println!("Subscription already configured correctly.\n");
-            } else {
-                println!("Setting the target subscription to {}\n", &subscription);
-                set_target_subscription(subscription)?;
+    if let Some(t) = tenant {
+        if !account.is_tenant(t) {
+            if let Some(ref tenant_id) = account.tenant_id {
+                account.tenant_domains.extend(get_tenant_domains(tenant_id));
             }
         }
+        if !account.is_tenant(t) {
+            println!("Logging in to the {} tenant.", t);
+            login(&credential.credential()?)?;
+            account = get_account_info()?;
+        }
+    }
+
+    if let Some(ref account_subscription) = account.subscription_name {
+        println!("The default subscription is {}", account_subscription);
+    }
+
+    if !subscription.is_empty() {
+        if account.is_subscription(subscription) {
+            println!("Subscription already configured correctly.\n");
+        } else {
+            println!("Setting the target subscription to {}\n", &subscription);
+            set_target_subscription(subscription)?;
+        }
     }
 
     Ok(())
 }
 
-fn get_account_info() -> Result<AzAccountInfo, AzCliError> {
+pub fn get_account_info() -> Result<AzAccountInfo, AzCliError> {
     let args = vec!["account", "show", "--output", "json"];
 
     let output = run_az_command_with_output(args)?;
     let stdout = String::from_utf8(output.stdout)?;
 
+    if !output.status.success() {
+        return Err(AzCliError::NotLoggedIn);
+    }
+
+    parse_account(&stdout)
+}
+
+// Only the default domain of a tenant is in `az account show`, its other domains are listed with
+// the tenants.  A tenant that cannot be listed has no other domains.
+fn get_tenant_domains(tenant_id: &str) -> Vec<String> {
+    let args = vec!["account", "tenant", "list", "--output", "json"];
+    let tenants: Vec<TenantList> = match run_az_command_with_output(args) {
+        Ok(output) if output.status.success() => {
+            serde_json::from_slice(&output.stdout).unwrap_or_default()
+        }
+        _ => Vec::new(),
+    };
+    parse_tenant_domains(tenants, tenant_id)
+}
+
+fn parse_tenant_domains(tenants: Vec<TenantList>, tenant_id: &str) -> Vec<String> {
+    tenants
+        .into_iter()
+        .filter(|t| t.tenant_id.eq_ignore_ascii_case(tenant_id))
+        .flat_map(|t| t.domains)
+        .collect()
+}
+
+pub fn can_access_subscription(subscription: &str) -> Result<bool, AzCliError> {
+    let args = vec![
+        "account",
+        "show",
+        "--subscription",
+        subscription,
+        "--output",
+        "json",
+    ];
+
+    let output = run_az_command_with_output(args)?;
+    Ok(output.status.success())
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct AzVersion {
+    #[serde(rename = "azure-cli")]
+    pub cli: String,
+    #[serde(default)]
+    pub extensions: HashMap<String, String>,
+}
+
+pub fn get_az_version() -> Result<AzVersion, AzCliError> {
+    let args = vec!["version", "--output", "json"];
+
+    let output = run_az_command_with_output(args)?;
+    let stdout = String::from_utf8(output.stdout)?;
+    Ok(serde_json::from_str(&stdout)?)
+}
+
+// `group show` for the resource group, or None when it does not exist.
+pub fn show_resource_group(name: &str, subscription: &str) -> Result<Option<Value>, AzCliError> {
+    let mut args = vec!["group", "show", "--name", name, "--output", "json"];
+    if !subscription.is_empty() {
+        args.push("--subscription");
+        args.push(subscription);
+    }
+    show(args)
+}
+
+// `group deployment show` for the deployment, or None when it does not exist.
+pub fn show_deployment(
+    resource_group: &str,
+    name: &str,
+    subscription: &str,
+) -> Result<Option<Value>, AzCliError> {
+    let mut args = vec![
+        "group",
+        "deployment",
+        "show",
+        "--resource-group",
+        resource_group,
+        "--name",
+        name,
+        "--output",
+        "json",
+    ];
+    if !subscription.is_empty() {
+        args.push("--subscription");
+        args.push(subscription);
+    }
+    show(args)
+}
+
+fn show(args: Vec<&str>) -> Result<Option<Value>, AzCliError> {
+    let output = run_az_command_with_output(args)?;
+    if output.status.code() == Some(NOT_FOUND_EXIT_CODE) {
+        return Ok(None);
+    }
+    check_status(&output)?;
+    Ok(Some(serde_json::from_slice(&output.stdout)?))
+}
+
+fn parse_account(stdout: &str) -> Result<AzAccountInfo, AzCliError> {
     let regex_string = "Please run 'az login' to setup account.";
     let re = Regex::new(regex_string)?;
 
-    let account = AzAccountInfo::default();
+    if re.is_match(stdout) {
+        return Err(AzCliError::NotLoggedIn);
+    }
 
-    let mut _return_value = Ok(account);
+    let account: AccountShow = serde_json::from_str(stdout)?;
+    Ok(AzAccountInfo {
+        subscription_id: Some(account.id),
+        subscription_name: Some(account.name),
+        tenant_id: Some(account.tenant_id),
+        tenant_domains: account.tenant_default_domain.into_iter().collect(),
+    })
+}
 
-    if let Some(_captures) = re.captures(&stdout) {
-        _return_value = Err(AzCliError::NotLoggedIn);
-    } else {
-        let v: Value = serde_json::from_str(&stdout)?;
+fn login(credential: &Credential) -> Result<(), AzCliError> {
+    if credential.is_interactive() {
+        return interactive_login(credential);
+    }
 
-        let current_account = AzAccountInfo {
-            subscription_id: Some(v["id"].to_string()),
-            subscription_name: Some(v["name"].to_string()),
-            tenant_id: Some(v["tenantId"].to_string()),
-        };
+    println!("Logging in to the Azure CLI non-interactively.");
+    let args = credential.login_arguments();
+    let p: Vec<&str> = args.iter().map(|s| &**s).collect();
+    let output = run_az_command_with_output(p)?;
 
-        _return_value = Ok(current_account);
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(AzCliError::LoginFailed {
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        })
     }
-
-    _return_value
 }
 
-fn login() -> Result<(), AzCliError> {
+fn interactive_login(credential: &Credential) -> Result<(), AzCliError> {
+    // Interactive logins cannot be recorded, so a replayed session is assumed to be logged in.
+    if replay_cassette().is_some() {
+        return Ok(());
+    }
+
     let az_cli_path = get_az_cli_path()?;
     let (pipe_reader, _pipe_writer) = os_pipe::pipe()?;
     let (error_pipe_reader, error_pipe_writer) = os_pipe::pipe()?;
 
-    let args = vec!["login"];
+    let args = credential.login_arguments();
 
     let _child = cmd(az_cli_path, &args)
         .stdout_handle(pipe_reader)
@@ -137,126 +369,262 @@ fn login() -> Result<(), AzCliError> {
     Ok(())
 }
 
-fn set_target_subscription(subscription_name: &str) -> Result<AzAccountInfo, AzCliError> {
+fn set_target_subscription(subscription: &str) -> Result<AzAccountInfo, AzCliError> {
     let mut account = get_account_info()?;
 
-    if account.subscription_name != Some(String::from(subscription_name)) {
-        let args = vec!["account", "set", "--subscription", subscription_name];
+    if !account.is_subscription(subscription) {
+        let args = vec!["account", "set", "--subscription", subscription];
         let _output = run_az_command_with_output(args)?;
         account = get_account_info()?;
     }
 
-    Ok(account)
+    if account.is_subscription(subscription) {
+        Ok(account)
+    } else {
+        Err(AzCliError::SubscriptionNotFound {
+            subscription: subscription.to_string(),
+        })
+    }
+}
+
+pub struct AzCliBackend;
+
+impl CloudBackend for AzCliBackend {
+    fn ensure_resource_group(&self, command: &Command) -> Result<(), CloudBackendError> {
+        let output = create_resource_group(command)?;
+        check_status(&output)?;
+        Ok(())
+    }
+
+    fn deploy_template(&self, command: &Command) -> Result<(), CloudBackendError> {
+        let output = deploy_template(command)?;
+        check_status(&output)?;
+        Ok(())
+    }
+
+    fn delete_resource_group(&self, name: &str) -> Result<(), CloudBackendError> {
+        let args = vec!["group", "delete", "--name", name, "--yes", "--no-wait"];
+        let output = run_az_command_with_output(args)?;
+        check_status(&output)?;
+        Ok(())
+    }
+
+    fn list_resource_groups(&self, tag: &str) -> Result<Vec<ResourceGroup>, CloudBackendError> {
+        let args = vec!["group", "list", "--tag", tag, "--output", "json"];
+        let output = run_az_command_with_output(args)?;
+        check_status(&output)?;
+        Ok(serde_json::from_slice(&output.stdout)?)
+    }
+
+    fn show_account(&self) -> Result<AzAccountInfo, CloudBackendError> {
+        Ok(get_account_info()?)
+    }
+}
+
+fn check_status(output: &Output) -> Result<(), AzCliError> {
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(AzCliError::CliFailed {
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        })
+    }
+}
+
+// Returns a bearer token for Azure Resource Manager and the id of the subscription it is for.
+pub fn get_access_token(subscription: &str) -> Result<(String, String), AzCliError> {
+    let mut args = vec!["account", "get-access-token", "--output", "json"];
+    if !subscription.is_empty() {
+        args.push("--subscription");
+        args.push(subscription);
+    }
+
+    let output = run_az_command_with_output(args)?;
+    let stdout = String::from_utf8(output.stdout)?;
+    let v: Value = serde_json::from_str(&stdout)?;
+
+    match (v["accessToken"].as_str(), v["subscription"].as_str()) {
+        (Some(token), Some(subscription_id)) => {
+            Ok((token.to_string(), subscription_id.to_string()))
+        }
+        _ => Err(AzCliError::NotLoggedIn),
+    }
 }
 
 fn create_resource_group(command: &Command) -> Result<Output, AzCliError> {
     let local_command = command.clone();
     let rg = local_command.resource_group.unwrap();
-    let location = local_command.location.unwrap();
-    let args = vec!["group", "create", "--name", &rg, "--location", &location];
+    let location = local_command
+        .location
+        .ok_or_else(|| AzCliError::MissingLocation {
+            resource_group: rg.to_string(),
+        })?;
+    let mut tags = command.tags();
+    if let Some(existing) = show_resource_group(&rg, "")? {
+        keep_created_tag(&mut tags, &existing);
+    }
+    let tags = tag_arguments(&tags);
+    let mut args = vec!["group", "create", "--name", &rg, "--location", &location];
+    if !tags.is_empty() {
+        args.push("--tags");
+        args.extend(tags.iter().map(|t| t.as_str()));
+    }
     run_az_command_with_output(args)
 }
 
-pub fn run_cli_command(command: &Command) -> Result<Output, AzCliError> {
-    create_resource_group(command)?;
-
-    let mut args: Vec<String> = Vec::new();
-    let cli_command = command.cli();
-    args.push(cli_command.subcommand());
+fn tag_arguments(tags: &Tags) -> Vec<String> {
+    tags.iter().map(|(k, v)| format!("{}={}", k, v)).collect()
+}
 
-    if cli_command.parameters.is_some() {
-        let parameters = cli_command.parameters();
-        args.extend(parameters);
-    }
+pub fn run_cli_command(command: &Command) -> Result<Output, AzCliError> {
+    check_status(&create_resource_group(command)?)?;
 
+    let args = cli_arguments(command);
     let p: Vec<&str> = args.iter().map(|s| &**s).collect();
     run_az_command_with_output(p)
 }
 
-pub fn deploy_template(command: &Command) -> Result<Output, AzCliError> {
-    create_resource_group(command)?;
-
-    let local_template = command.clone().template();
-    if local_template.path.is_some() {
-        deploy_template_from_file(command)
-    } else if local_template.url.is_some() {
-        deploy_template_from_url(command)
-    } else {
-        Err(AzCliError::MissingTemplate)
+// The Azure CLI command line for a `cli` command, as it would be typed.
+pub fn cli_command_line(command: &Command) -> String {
+    let mut line = vec!["az".to_string()];
+    for argument in cli_arguments(command) {
+        if argument.is_empty() || argument.contains(char::is_whitespace) {
+            line.push(format!("\"{}\"", argument));
+        } else {
+            line.push(argument);
+        }
     }
+    line.join(" ")
 }
 
-fn deploy_template_from_file(command: &Command) -> Result<Output, AzCliError> {
-    let local_command = command.clone();
-    let template = local_command.template();
-    let rg = local_command.resource_group.unwrap();
-    let path = template.path();
-    let parameters = template.parameters();
-    let deployment_name = format!("{}", Uuid::new_v4());
-    let mut args = vec![
-        "group",
-        "deployment",
-        "create",
-        "--name",
-        &deployment_name,
-        "--resource-group",
-        &rg,
-        "--template-file",
-        &path,
+fn cli_arguments(command: &Command) -> Vec<String> {
+    let cli_command = command.cli();
+    let mut args = cli_command.subcommand();
+    args.extend(cli_command.parameters());
+    args
+}
+
+pub fn deploy_template(command: &Command) -> Result<Output, AzCliError> {
+    check_status(&create_resource_group(command)?)?;
+
+    let deployment_name = command.deployment_name();
+    let mut args: Vec<String> = vec![
+        "group".to_string(),
+        "deployment".to_string(),
+        "create".to_string(),
+        "--name".to_string(),
+        deployment_name,
+        "--mode".to_string(),
+        "Incremental".to_string(),
     ];
+    // The parameters file is deleted when it goes out of scope, once `az` has returned.
+    let (template_args, _parameters_file) = template_arguments(command)?;
+    args.extend(template_args);
 
-    if template.parameters.is_some() {
-        args.push("--parameters");
+    let p: Vec<&str> = args.iter().map(|s| &**s).collect();
+    run_az_command_with_output(p)
+}
 
-        let p: Vec<&str> = parameters.iter().map(|s| &**s).collect();
-        args.extend(p);
+// What-if needs the target resource group to exist before it can compare against it, and a
+// preview must not create anything, so a missing group is only reported.
+pub fn preview_template(command: &Command) -> Result<Option<Output>, AzCliError> {
+    let rg = command.resource_group.clone().unwrap();
+    let subscription = command.subscription.clone().unwrap_or_default();
+    if show_resource_group(&rg, &subscription)?.is_none() {
+        println!(
+            "\t\tThe {} resource group does not exist, it would be created in {} and the template deployed into it.",
+            rg,
+            command.location.clone().unwrap_or_default()
+        );
+        return Ok(None);
     }
 
-    run_az_command_with_output(args)
+    let deployment_name = command.deployment_name();
+    let mut args: Vec<String> = vec![
+        "deployment".to_string(),
+        "group".to_string(),
+        "what-if".to_string(),
+        "--name".to_string(),
+        deployment_name,
+    ];
+    let (template_args, _parameters_file) = template_arguments(command)?;
+    args.extend(template_args);
+
+    let p: Vec<&str> = args.iter().map(|s| &**s).collect();
+    Ok(Some(run_az_command_with_output(p)?))
 }
 
-fn deploy_template_from_url(command: &Command) -> Result<Output, AzCliError> {
+// Resource group, template source and parameters shared by deployments and what-if previews,
+// along with the parameters file they refer to.
+fn template_arguments(
+    command: &Command,
+) -> Result<(Vec<String>, Option<NamedTempFile>), AzCliError> {
     let local_command = command.clone();
-    let template = local_command.template.unwrap();
+    let template = local_command.template();
     let rg = local_command.resource_group.unwrap();
-    let parameters = template.parameters();
-    let url = template.url();
-    let deployment_name = format!("{}", Uuid::new_v4());
-    let mut args = vec![
-        "group",
-        "deployment",
-        "create",
-        "--name",
-        &deployment_name,
-        "--resource-group",
-        &rg,
-        "--template-uri",
-        &url,
-    ];
 
-    if template.parameters.is_some() {
-        args.push("--parameters");
+    let mut args = vec!["--resource-group".to_string(), rg];
+    if template.path.is_some() {
+        args.push("--template-file".to_string());
+        args.push(template.path());
+    } else if template.url.is_some() {
+        args.push("--template-uri".to_string());
+        args.push(template.url());
+    } else {
+        return Err(AzCliError::MissingTemplate);
+    }
 
-        let p: Vec<&str> = parameters.iter().map(|s| &**s).collect();
-        args.extend(p);
+    let mut parameters_file = None;
+    if template.has_parameters() {
+        let file = write_parameters_file(command)?;
+        args.push("--parameters".to_string());
+        args.push(format!("@{}", file.path().display()));
+        parameters_file = Some(file);
     }
 
-    run_az_command_with_output(args)
+    Ok((args, parameters_file))
+}
+
+// Typed parameters are passed to the Azure CLI as an ARM parameters file rather than as
+// key=value pairs, so values keep their types and can contain spaces or `=`.  Parameters can
+// be secrets, so the file has a unique name and is only readable by the current user.
+fn write_parameters_file(command: &Command) -> Result<NamedTempFile, AzCliError> {
+    let parameters = command.template().parameters()?;
+    let mut file = Builder::new()
+        .prefix(PARAMETERS_FILE_PREFIX)
+        .suffix(".parameters.json")
+        .tempfile()?;
+    file.write_all(serde_json::to_string_pretty(&parameters)?.as_bytes())?;
+    file.flush()?;
+
+    Ok(file)
 }
 
 fn run_az_command_with_output(args: Vec<&str>) -> Result<Output, AzCliError> {
+    if let Some(cassette) = replay_cassette() {
+        return Ok(replay(&cassette, &args)?.output());
+    }
+
     let az_cli_path = get_az_cli_path()?;
+    let output = run_az_command(&az_cli_path, &args)?;
 
-    let output = cmd(az_cli_path, &args)
-        .stderr_to_stdout()
-        .stdout_capture()
-        .unchecked()
-        .run()?;
+    if let Ok(cassette) = env::var(RECORD_VARIABLE) {
+        record(Path::new(&cassette), Interaction::new(&args, &output))?;
+    }
 
     Ok(output)
 }
 
-fn find_command<T>(command: T) -> Option<PathBuf>
+// Warnings and errors go to stderr, so stdout only has the JSON the commands are asked for.
+fn run_az_command(az_cli_path: &Path, args: &[&str]) -> Result<Output, AzCliError> {
+    Ok(cmd(az_cli_path, args)
+        .stdout_capture()
+        .stderr_capture()
+        .unchecked()
+        .run()?)
+}
+
+pub fn find_command<T>(command: T) -> Option<PathBuf>
 where
     T: AsRef<Path>,
 {
@@ -298,3 +666,331 @@ fn find_command_with_pathext(candidate: &PathBuf) -> Option<PathBuf> {
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::cassette::use_replay_cassette;
+
+    const ACCOUNT_SHOW: &str = r#"{
+  "environmentName": "AzureCloud",
+  "id": "0F8FAD5B-D9CB-469F-A165-70867728950E",
+  "isDefault": true,
+  "name": "Ignite the Tour",
+  "state": "Enabled",
+  "tenantDefaultDomain": "contoso.onmicrosoft.com",
+  "tenantId": "7c9e6679-7425-40de-944b-e07fc1f90ae7"
+}"#;
+
+    #[test]
+    fn azure_config_directory_is_per_profile() {
+        let tour = azure_config_directory("tour").unwrap();
+        let rehearsal = azure_config_directory("rehearsal").unwrap();
+
+        assert!(tour.ends_with("demo-up/azure/tour"));
+        assert_ne!(tour, rehearsal);
+    }
+
+    #[test]
+    fn azure_config_directory_is_a_safe_path() {
+        let directory = azure_config_directory("DOMAIN\\some user/../x").unwrap();
+
+        assert!(directory.ends_with("DOMAIN_some_user_.._x"));
+    }
+
+    #[test]
+    fn azure_config_directory_stays_in_the_profiles_directory() {
+        let profiles = azure_config_directory("tour").unwrap();
+        let profiles = profiles.parent().unwrap();
+
+        for name in &["..", ".", ""] {
+            let directory = azure_config_directory(name).unwrap();
+            assert_eq!(directory.parent(), Some(profiles));
+            assert!(directory
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with('_'));
+        }
+    }
+
+    #[test]
+    fn account_fields_are_not_quoted() {
+        let account = parse_account(ACCOUNT_SHOW).unwrap();
+
+        assert_eq!(
+            account.subscription_name,
+            Some("Ignite the Tour".to_string())
+        );
+        assert_eq!(
+            account.tenant_id,
+            Some("7c9e6679-7425-40de-944b-e07fc1f90ae7".to_string())
+        );
+    }
+
+    #[test]
+    fn subscription_matches_name_or_id() {
+        let account = parse_account(ACCOUNT_SHOW).unwrap();
+
+        assert!(account.is_subscription("Ignite the Tour"));
+        assert!(account.is_subscription("0f8fad5b-d9cb-469f-a165-70867728950e"));
+        assert!(!account.is_subscription("ignite the tour"));
+        assert!(!account.is_subscription("Other subscription"));
+    }
+
+    #[test]
+    fn tenant_matches_id_or_domain() {
+        let mut account = parse_account(ACCOUNT_SHOW).unwrap();
+
+        assert!(account.is_tenant("7C9E6679-7425-40DE-944B-E07FC1F90AE7"));
+        assert!(account.is_tenant("Contoso.onmicrosoft.com"));
+        assert!(!account.is_tenant("contoso.com"));
+        assert!(!account.is_tenant("fabrikam.onmicrosoft.com"));
+
+        let tenants: Vec<TenantList> = serde_json::from_str(
+            r#"[
+  {"tenantId": "7c9e6679-7425-40de-944b-e07fc1f90ae7", "domains": ["contoso.com", "contoso.onmicrosoft.com"]},
+  {"tenantId": "2d1f4d9a-0000-0000-0000-000000000000", "domains": ["fabrikam.onmicrosoft.com"]}
+]"#,
+        )
+        .unwrap();
+        account.tenant_domains.extend(parse_tenant_domains(
+            tenants,
+            "7c9e6679-7425-40de-944b-e07fc1f90ae7",
+        ));
+        assert!(account.is_tenant("contoso.com"));
+        assert!(!account.is_tenant("fabrikam.onmicrosoft.com"));
+    }
+
+    #[test]
+    fn cli_command_lines_quote_arguments_with_spaces() {
+        let command: Command = serde_yaml::from_str(
+            "
+cli:
+  subcommand: webapp config appsettings set
+  parameters:
+    - --settings
+    - GREETING=Hello world
+",
+        )
+        .unwrap();
+
+        assert_eq!(
+            cli_command_line(&command),
+            "az webapp config appsettings set --settings \"GREETING=Hello world\""
+        );
+    }
+
+    #[test]
+    fn parameters_files_are_private_and_deleted() {
+        let command: Command = serde_yaml::from_str(
+            "
+resource_group: SRE10-tour
+deployment_name: SRE10-tour-1
+template:
+  path: ./test/artifacts/demo_template.json
+  parameters:
+    adminPassword: secret
+",
+        )
+        .unwrap();
+
+        let (args, file) = template_arguments(&command).unwrap();
+        let path = file.as_ref().unwrap().path().to_path_buf();
+        let content = std::fs::read_to_string(&path).unwrap();
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::metadata(&path).unwrap().permissions().mode() & 0o777
+        };
+        drop(file);
+
+        assert_eq!(args.last().unwrap(), &format!("@{}", path.display()));
+        assert!(content.contains("\"adminPassword\""));
+        #[cfg(unix)]
+        assert_eq!(mode, 0o600);
+        assert!(!path.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn errors_are_read_from_stderr() {
+        let output = run_az_command(
+            Path::new("/bin/sh"),
+            &[
+                "-c",
+                "echo 'WARNING: This command is in preview.' >&2; echo '[]'",
+            ],
+        )
+        .unwrap();
+        check_status(&output).unwrap();
+        let groups: Vec<ResourceGroup> = serde_json::from_slice(&output.stdout).unwrap();
+        assert!(groups.is_empty());
+
+        let output = run_az_command(
+            Path::new("/bin/sh"),
+            &[
+                "-c",
+                "echo \"ERROR: (AuthorizationFailed) No access.\" >&2; exit 1",
+            ],
+        )
+        .unwrap();
+        match check_status(&output) {
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "The Azure CLI failed: ERROR: (AuthorizationFailed) No access."
+            ),
+            Ok(_) => panic!("Expected the command to fail."),
+        }
+    }
+
+    #[test]
+    fn login_errors_are_reported() {
+        let directory = tempfile::tempdir().unwrap();
+        let cassette = directory.path().join("login.json");
+        let credential = CredentialOptions {
+            client_id: Some("demo-up".to_string()),
+            tenant: Some("contoso.onmicrosoft.com".to_string()),
+            client_secret: Some("secret".to_string()),
+            ..Default::default()
+        }
+        .credential()
+        .unwrap();
+        let args = credential.login_arguments();
+        let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+        let failed = Interaction {
+            args: Vec::new(),
+            exit_code: 1,
+            stdout: String::new(),
+            stderr: "ERROR: AADSTS7000215: Invalid client secret provided.\n".to_string(),
+        };
+        record(&cassette, Interaction::new(&args, &failed.output())).unwrap();
+        use_replay_cassette(&cassette);
+
+        match login(&credential) {
+            Err(AzCliError::LoginFailed { message }) => assert_eq!(
+                message,
+                "ERROR: AADSTS7000215: Invalid client secret provided."
+            ),
+            r => panic!("Expected the login to fail, got {:?}", r),
+        }
+    }
+
+    fn deployment_command() -> Command {
+        serde_yaml::from_str(
+            "
+resource_group: SRE10-tour
+location: westus2
+deployment_name: SRE10-tour-p1
+template:
+  path: ./test/artifacts/demo_template.json
+",
+        )
+        .unwrap()
+    }
+
+    fn use_cassette(directory: &Path, interactions: Vec<(&[&str], i32, &str)>) {
+        let cassette = directory.join("cassette.json");
+        for (args, exit_code, stderr) in interactions {
+            let interaction = Interaction {
+                args: args.iter().map(|a| a.to_string()).collect(),
+                exit_code,
+                stdout: if exit_code == 0 { "{}" } else { "" }.to_string(),
+                stderr: stderr.to_string(),
+            };
+            record(&cassette, interaction).unwrap();
+        }
+        use_replay_cassette(&cassette);
+    }
+
+    const GROUP_SHOW: &[&str] = &["group", "show", "--name", "SRE10-tour", "--output", "json"];
+    const GROUP_CREATE: &[&str] = &[
+        "group",
+        "create",
+        "--name",
+        "SRE10-tour",
+        "--location",
+        "westus2",
+    ];
+
+    #[test]
+    fn failed_deployments_are_errors() {
+        let directory = tempfile::tempdir().unwrap();
+        use_cassette(
+            directory.path(),
+            vec![
+                (GROUP_SHOW, 3, "ERROR: (ResourceGroupNotFound)"),
+                (GROUP_CREATE, 0, ""),
+                (
+                    &[
+                        "group",
+                        "deployment",
+                        "create",
+                        "--name",
+                        "SRE10-tour-p1",
+                        "--mode",
+                        "Incremental",
+                        "--resource-group",
+                        "SRE10-tour",
+                        "--template-file",
+                        "./test/artifacts/demo_template.json",
+                    ],
+                    1,
+                    "ERROR: (InvalidTemplateDeployment) The template deployment is not valid.",
+                ),
+            ],
+        );
+
+        match AzCliBackend.deploy_template(&deployment_command()) {
+            Err(CloudBackendError::AzCliFailure {
+                source: AzCliError::CliFailed { message },
+            }) => assert_eq!(
+                message,
+                "ERROR: (InvalidTemplateDeployment) The template deployment is not valid."
+            ),
+            r => panic!("Expected the deployment to fail, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn failed_resource_group_lookups_are_errors() {
+        let directory = tempfile::tempdir().unwrap();
+        use_cassette(
+            directory.path(),
+            vec![(GROUP_SHOW, 1, "ERROR: (AuthorizationFailed) No access.")],
+        );
+
+        match AzCliBackend.ensure_resource_group(&deployment_command()) {
+            Err(CloudBackendError::AzCliFailure {
+                source: AzCliError::CliFailed { message },
+            }) => assert_eq!(message, "ERROR: (AuthorizationFailed) No access."),
+            r => panic!("Expected the lookup to fail, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn failed_resource_groups_are_errors() {
+        let directory = tempfile::tempdir().unwrap();
+        use_cassette(
+            directory.path(),
+            vec![
+                (GROUP_SHOW, 3, "ERROR: (ResourceGroupNotFound)"),
+                (GROUP_CREATE, 1, "ERROR: (LocationNotAvailable)"),
+            ],
+        );
+
+        assert!(AzCliBackend
+            .ensure_resource_group(&deployment_command())
+            .is_err());
+    }
+
+    #[test]
+    fn not_logged_in_is_detected() {
+        let result = parse_account("Please run 'az login' to setup account.");
+
+        match result {
+            Err(AzCliError::NotLoggedIn) => {}
+            _ => panic!("Expected the account to be logged out."),
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use super::arm_rest::ArmRestBackend;
+use super::az_cli::{get_access_token, AzAccountInfo, AzCliBackend, AzCliError};
+use config::arm_template::ParametersFileError;
+use config::tags::CREATED_TAG;
+use config::{Command, Tags};
+use custom_error::custom_error;
+use serde::Deserialize;
+use serde_json::Value;
+use std::env;
+
+custom_error! {
+    pub CloudBackendError
+    AzCliFailure{source: AzCliError} = "The Azure CLI failed: {source}",
+    RequestFailure{source: reqwest::Error} = "The request to Azure Resource Manager failed: {source}",
+    JsonError{source: serde_json::Error} = "Failed to process a JSON document.",
+    IoError{source: std::io::Error} = "Failed to read a local file.",
+    ParametersError{source: ParametersFileError} = "Failed to prepare the template parameters.",
+    UnexpectedResponse{status: u16, body: String} = "Azure Resource Manager returned {status}: {body}",
+    DeploymentFailed{name: String, state: String} = "Deployment {name} finished as {state}.",
+    DeploymentTimedOut{name: String, minutes: u64} = "Deployment {name} did not finish within {minutes} minutes.",
+    MissingTemplate = "No template available to deploy",
+    MissingLocation{resource_group: String} = "No location for the {resource_group} resource group.",
+    UnknownBackend{name: String} = "Unknown backend {name}.",
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct ResourceGroup {
+    pub name: String,
+    pub location: String,
+    pub tags: Option<Tags>,
+}
+
+impl ResourceGroup {
+    pub fn tags(&self) -> Tags {
+        self.clone().tags.unwrap_or_default()
+    }
+}
+
+// Operations against Azure that can be performed either through the Azure CLI or directly
+// against the Azure Resource Manager REST API.
+pub trait CloudBackend {
+    fn ensure_resource_group(&self, command: &Command) -> Result<(), CloudBackendError>;
+    fn deploy_template(&self, command: &Command) -> Result<(), CloudBackendError>;
+    fn delete_resource_group(&self, name: &str) -> Result<(), CloudBackendError>;
+    // Resource groups carrying the given tag, whatever its value.
+    fn list_resource_groups(&self, tag: &str) -> Result<Vec<ResourceGroup>, CloudBackendError>;
+    fn show_account(&self) -> Result<AzAccountInfo, CloudBackendError>;
+}
+
+// A resource group that already exists keeps the time it was first created, so `demo gc
+// --older-than` counts from then rather than from the last run.
+pub fn keep_created_tag(tags: &mut Tags, existing: &Value) {
+    if let Some(created) = existing["tags"][CREATED_TAG].as_str() {
+        tags.insert(CREATED_TAG.to_string(), created.to_string());
+    }
+}
+
+pub const AZ_CLI_BACKEND: &str = "az";
+pub const ARM_REST_BACKEND: &str = "rest";
+
+const ARM_ENDPOINT_VARIABLE: &str = "AZURE_RESOURCE_MANAGER_ENDPOINT";
+pub const ACCESS_TOKEN_VARIABLE: &str = "AZURE_ACCESS_TOKEN";
+const DEFAULT_ARM_ENDPOINT: &str = "https://management.azure.com";
+
+pub fn get_backend(
+    name: &str,
+    subscription: &str,
+) -> Result<Box<dyn CloudBackend>, CloudBackendError> {
+    match name {
+        AZ_CLI_BACKEND => Ok(Box::new(AzCliBackend)),
+        ARM_REST_BACKEND => {
+            let endpoint = env::var(ARM_ENDPOINT_VARIABLE)
+                .unwrap_or_else(|_| DEFAULT_ARM_ENDPOINT.to_string());
+            // A token supplied in the environment avoids needing the Azure CLI at all, in which
+            // case the subscription has to be given by its id.
+            let (token, subscription_id) = match env::var(ACCESS_TOKEN_VARIABLE) {
+                Ok(token) => (token, subscription.to_string()),
+                Err(_) => get_access_token(subscription)?,
+            };
+            Ok(Box::new(ArmRestBackend::new(
+                &endpoint,
+                &token,
+                &subscription_id,
+            )))
+        }
+        _ => Err(CloudBackendError::UnknownBackend {
+            name: name.to_string(),
+        }),
+    }
+}
@@ -0,0 +1,411 @@
+use super::az_cli::PARAMETERS_FILE_PREFIX;
+use crate::config::tags::{CREATED_TAG, EXPIRES_TAG, VERSION_TAG};
+use crate::config::verify::sha256;
+use custom_error::custom_error;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Output};
+
+// Setting one of these to a file path records every Azure CLI invocation to that cassette, or
+// serves the invocations back from it without running the Azure CLI.
+pub const RECORD_VARIABLE: &str = "DEMO_AZ_RECORD";
+pub const REPLAY_VARIABLE: &str = "DEMO_AZ_REPLAY";
+
+custom_error! {
+    pub CassetteError
+    IoError{source: std::io::Error} = "Failed to access the cassette file.",
+    JsonError{source: serde_json::Error} = "Failed to parse the cassette file.",
+    MissingInteraction{args: String} = "No recorded interaction for `az {args}`.",
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Interaction {
+    pub args: Vec<String>,
+    pub exit_code: i32,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl Interaction {
+    pub fn new(args: &[&str], output: &Output) -> Self {
+        Interaction {
+            args: redact(args),
+            exit_code: output.status.code().unwrap_or(-1),
+            stdout: redact_output(&String::from_utf8_lossy(&output.stdout)),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        }
+    }
+
+    pub fn output(&self) -> Output {
+        Output {
+            status: exit_status(self.exit_code),
+            stdout: self.stdout.clone().into_bytes(),
+            stderr: self.stderr.clone().into_bytes(),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    ExitStatus::from_raw(code << 8)
+}
+
+#[cfg(windows)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::windows::process::ExitStatusExt;
+    ExitStatus::from_raw(code as u32)
+}
+
+const REDACTED: &str = "REDACTED";
+const VOLATILE: &str = "*";
+
+// Secrets (like service principal passwords) never end up in a cassette, and tag values that
+// change on every run (like the creation time) are masked so recordings still match.
+fn redact(args: &[&str]) -> Vec<String> {
+    let mut redacted = Vec::new();
+    let mut secret = false;
+    for arg in args {
+        if secret {
+            redacted.push(REDACTED.to_string());
+        } else if let Some(tag) = volatile_tag(arg) {
+            redacted.push(format!("{}={}", tag, VOLATILE));
+        } else if is_parameters_file(arg) {
+            redacted.push(parameters_file_argument(arg, args));
+        } else {
+            redacted.push(arg.to_string());
+        }
+        secret = *arg == "--password" || *arg == "-p";
+    }
+    redacted
+}
+
+// Access tokens (from `az account get-access-token`) are secrets too.
+fn redact_output(output: &str) -> String {
+    let token = Regex::new(r#"("accessToken"\s*:\s*")[^"]*""#).unwrap();
+    token
+        .replace_all(output, format!("${{1}}{}\"", REDACTED).as_str())
+        .to_string()
+}
+
+// Parameters files have a different temporary name on every run.
+fn is_parameters_file(arg: &str) -> bool {
+    arg.starts_with('@')
+        && Path::new(&arg[1..])
+            .file_name()
+            .map(|n| n.to_string_lossy().starts_with(PARAMETERS_FILE_PREFIX))
+            .unwrap_or(false)
+}
+
+// Parameters files are matched by a hash of their content instead, with the values of secure
+// parameters (or of every parameter, when the template is not a local file) redacted first.
+fn parameters_file_argument(arg: &str, args: &[&str]) -> String {
+    let mut document: Value = match fs::read_to_string(&arg[1..])
+        .ok()
+        .and_then(|c| serde_json::from_str(&c).ok())
+    {
+        Some(d) => d,
+        None => return format!("@{}", VOLATILE),
+    };
+    let secure = secure_parameters(args);
+    if let Some(parameters) = document["parameters"].as_object_mut() {
+        for (name, parameter) in parameters.iter_mut() {
+            let is_secure = match secure {
+                Some(ref s) => s.contains(&name.to_lowercase()),
+                None => true,
+            };
+            match parameter.as_object_mut() {
+                Some(p) if is_secure && p.contains_key("value") => {
+                    p.insert("value".to_string(), Value::from(REDACTED));
+                }
+                _ => {}
+            }
+        }
+    }
+    format!("@sha256:{}", sha256(&document.to_string()))
+}
+
+// The lower case names of the parameters the local template declares as secure.
+fn secure_parameters(args: &[&str]) -> Option<Vec<String>> {
+    let position = args.iter().position(|a| *a == "--template-file")?;
+    let content = fs::read_to_string(args.get(position + 1)?).ok()?;
+    let template: Value = serde_json::from_str(&content).ok()?;
+    let parameters = template["parameters"].as_object()?;
+    Some(
+        parameters
+            .iter()
+            .filter(|(_, p)| {
+                p["type"]
+                    .as_str()
+                    .map(|t| t.to_lowercase().starts_with("secure"))
+                    .unwrap_or(false)
+            })
+            .map(|(name, _)| name.to_lowercase())
+            .collect(),
+    )
+}
+
+fn volatile_tag(arg: &str) -> Option<&str> {
+    let tags = [CREATED_TAG, EXPIRES_TAG, VERSION_TAG];
+    tags.iter()
+        .find(|t| arg.starts_with(&format!("{}=", t)))
+        .cloned()
+}
+
+thread_local! {
+    // A cassette set on a command, which takes the place of the replay variable for the thread
+    // running the command.
+    static CASSETTE: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+
+    // How many times each argument list has been replayed, so repeated invocations (like
+    // `account show` before and after logging in) are served in the order they were recorded.
+    static REPLAYED: RefCell<HashMap<Vec<String>, usize>> = RefCell::new(HashMap::new());
+}
+
+// Replaying starts over from the first interaction whenever a cassette is set.
+pub fn use_replay_cassette(path: &Path) {
+    CASSETTE.with(|cassette| *cassette.borrow_mut() = Some(path.to_path_buf()));
+    REPLAYED.with(|replayed| replayed.borrow_mut().clear());
+}
+
+pub fn replay_cassette() -> Option<PathBuf> {
+    CASSETTE
+        .with(|cassette| cassette.borrow().clone())
+        .or_else(|| env::var(REPLAY_VARIABLE).ok().map(PathBuf::from))
+}
+
+fn load(path: &Path) -> Result<Vec<Interaction>, CassetteError> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let mut content = String::new();
+    File::open(path)?.read_to_string(&mut content)?;
+    if content.trim().is_empty() {
+        Ok(Vec::new())
+    } else {
+        Ok(serde_json::from_str(&content)?)
+    }
+}
+
+pub fn record(path: &Path, interaction: Interaction) -> Result<(), CassetteError> {
+    let mut interactions = load(path)?;
+    interactions.push(interaction);
+
+    let mut file = File::create(path)?;
+    file.write_all(serde_json::to_string_pretty(&interactions)?.as_bytes())?;
+    Ok(())
+}
+
+pub fn replay(path: &Path, args: &[&str]) -> Result<Interaction, CassetteError> {
+    let args = redact(args);
+    let matching: Vec<Interaction> = load(path)?.into_iter().filter(|i| i.args == args).collect();
+
+    let position = REPLAYED.with(|replayed| {
+        let mut replayed = replayed.borrow_mut();
+        let count = replayed.entry(args.clone()).or_insert(0);
+        let position = *count;
+        *count += 1;
+        position
+    });
+
+    // Once the recorded interactions run out, keep serving the last one.
+    match matching.get(position).or_else(|| matching.last()) {
+        Some(i) => Ok(i.clone()),
+        None => Err(CassetteError::MissingInteraction {
+            args: args.join(" "),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    fn interaction(args: &[&str], stdout: &str) -> Interaction {
+        Interaction {
+            args: args.iter().map(|a| a.to_string()).collect(),
+            exit_code: 0,
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+        }
+    }
+
+    #[test]
+    fn recorded_interactions_are_replayed_in_order() {
+        let path = env::temp_dir().join("demo-up-cassette-order.json");
+        let _ = fs::remove_file(&path);
+
+        record(&path, interaction(&["account", "show"], "first")).unwrap();
+        record(&path, interaction(&["group", "list"], "groups")).unwrap();
+        record(&path, interaction(&["account", "show"], "second")).unwrap();
+
+        assert_eq!(replay(&path, &["account", "show"]).unwrap().stdout, "first");
+        assert_eq!(
+            replay(&path, &["account", "show"]).unwrap().stdout,
+            "second"
+        );
+        assert_eq!(
+            replay(&path, &["account", "show"]).unwrap().stdout,
+            "second"
+        );
+        assert_eq!(replay(&path, &["group", "list"]).unwrap().stdout, "groups");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn unknown_interactions_are_an_error() {
+        let path = env::temp_dir().join("demo-up-cassette-missing.json");
+        let _ = fs::remove_file(&path);
+
+        record(&path, interaction(&["account", "show"], "")).unwrap();
+
+        assert!(replay(&path, &["group", "list"]).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn passwords_are_redacted() {
+        let args = redact(&["login", "--service-principal", "--password", "secret"]);
+
+        assert_eq!(
+            args,
+            vec!["login", "--service-principal", "--password", REDACTED]
+        );
+    }
+
+    #[test]
+    fn access_tokens_are_redacted() {
+        let output = redact_output(
+            r#"{
+  "accessToken": "eyJ0eXAiOiJKV1Qi.eyJhdWQiOi.c2lnbmF0dXJl",
+  "expiresOn": "2019-11-04 10:30:00.000000",
+  "tokenType": "Bearer"
+}"#,
+        );
+
+        assert!(output.contains(r#""accessToken": "REDACTED""#));
+        assert!(!output.contains("eyJ0eXAiOiJKV1Qi"));
+        assert!(output.contains(r#""tokenType": "Bearer""#));
+    }
+
+    #[test]
+    fn volatile_tags_are_masked() {
+        let args = redact(&[
+            "--tags",
+            "demo-up-created=2019-11-04T09:30:00Z",
+            "demo-up-event=tour",
+        ]);
+
+        assert_eq!(
+            args,
+            vec!["--tags", "demo-up-created=*", "demo-up-event=tour"]
+        );
+    }
+
+    fn parameters_file(directory: &Path, content: &str) -> String {
+        let path = directory.join(format!("{}x1Yz.parameters.json", PARAMETERS_FILE_PREFIX));
+        fs::write(&path, content).unwrap();
+        format!("@{}", path.display())
+    }
+
+    #[test]
+    fn parameters_files_are_matched_by_content() {
+        let directory = tempfile::tempdir().unwrap();
+        let template = directory.path().join("template.json");
+        fs::write(
+            &template,
+            r#"{"parameters": {"webAppName": {"type": "string"}, "adminPassword": {"type": "secureString"}}}"#,
+        )
+        .unwrap();
+        let template = template.to_string_lossy().to_string();
+        let redacted = |content: &str| {
+            let file = parameters_file(directory.path(), content);
+            redact(&["--template-file", &template, "--parameters", &file])[3].clone()
+        };
+
+        let first = redacted(
+            r#"{"parameters": {"webAppName": {"value": "first"}, "adminPassword": {"value": "secret"}}}"#,
+        );
+        let second = redacted(
+            r#"{"parameters": {"webAppName": {"value": "second"}, "adminPassword": {"value": "secret"}}}"#,
+        );
+        let other_password = redacted(
+            r#"{"parameters": {"webAppName": {"value": "first"}, "adminPassword": {"value": "other"}}}"#,
+        );
+
+        assert!(first.starts_with("@sha256:"));
+        assert_ne!(first, second);
+        assert_eq!(first, other_password);
+    }
+
+    #[test]
+    fn parameters_files_without_a_local_template_hide_every_value() {
+        let directory = tempfile::tempdir().unwrap();
+        let first = parameters_file(
+            directory.path(),
+            r#"{"parameters": {"webAppName": {"value": "first"}}}"#,
+        );
+        let first = redact(&[
+            "--template-uri",
+            "https://example.com/t.json",
+            "--parameters",
+            &first,
+        ]);
+        let second = parameters_file(
+            directory.path(),
+            r#"{"parameters": {"webAppName": {"value": "second"}}}"#,
+        );
+        let second = redact(&[
+            "--template-uri",
+            "https://example.com/t.json",
+            "--parameters",
+            &second,
+        ]);
+
+        assert_eq!(first, second);
+        assert_eq!(
+            redact(&["--parameters", "@/tmp/demo-up-missing.parameters.json"]),
+            vec!["--parameters", "@*"]
+        );
+    }
+
+    #[test]
+    fn setting_a_cassette_replays_it_from_the_start() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("cassette.json");
+        record(&path, interaction(&["account", "show"], "first")).unwrap();
+        record(&path, interaction(&["account", "show"], "second")).unwrap();
+
+        use_replay_cassette(&path);
+        replay(&path, &["account", "show"]).unwrap();
+        assert_eq!(
+            replay(&path, &["account", "show"]).unwrap().stdout,
+            "second"
+        );
+
+        use_replay_cassette(&path);
+        assert_eq!(replay(&path, &["account", "show"]).unwrap().stdout, "first");
+    }
+
+    #[test]
+    fn replayed_output_keeps_the_exit_code() {
+        let mut failed = interaction(&["group", "show"], "");
+        failed.exit_code = 3;
+
+        let output = failed.output();
+
+        assert!(!output.status.success());
+        assert_eq!(output.status.code(), Some(3));
+    }
+}
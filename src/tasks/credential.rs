@@ -0,0 +1,243 @@
+use custom_error::custom_error;
+use serde::{Deserialize, Serialize};
+
+custom_error! {
+    pub CredentialError
+    MissingTenant = "A tenant is required to log in with a service principal.",
+    MissingSecret = "A client secret or certificate is required to log in with a service principal.",
+}
+
+// The login options as given.  They are only turned into a credential when the Azure CLI has to
+// log in, so stray options do not get in the way of a CLI that is already logged in.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CredentialOptions {
+    pub client_id: Option<String>,
+    pub tenant: Option<String>,
+    #[serde(skip_serializing)]
+    pub client_secret: Option<String>,
+    pub certificate_path: Option<String>,
+    pub managed_identity: bool,
+}
+
+impl CredentialOptions {
+    pub fn credential(&self) -> Result<Credential, CredentialError> {
+        Credential::from_options(
+            self.client_id.clone(),
+            self.tenant.clone(),
+            self.client_secret.clone(),
+            self.certificate_path.clone(),
+            self.managed_identity,
+        )
+    }
+}
+
+// How the Azure CLI should authenticate.  Service principals and managed identities allow
+// unattended provisioning, with the interactive device code flow as the fallback.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Credential {
+    ClientSecret {
+        client_id: String,
+        tenant: String,
+        client_secret: String,
+    },
+    ClientCertificate {
+        client_id: String,
+        tenant: String,
+        certificate_path: String,
+    },
+    ManagedIdentity {
+        client_id: Option<String>,
+        tenant: Option<String>,
+    },
+    DeviceCode {
+        tenant: Option<String>,
+    },
+}
+
+impl Credential {
+    pub fn from_options(
+        client_id: Option<String>,
+        tenant: Option<String>,
+        client_secret: Option<String>,
+        certificate_path: Option<String>,
+        managed_identity: bool,
+    ) -> Result<Credential, CredentialError> {
+        if managed_identity {
+            return Ok(Credential::ManagedIdentity { client_id, tenant });
+        }
+
+        match client_id {
+            Some(client_id) => {
+                let tenant = tenant.ok_or(CredentialError::MissingTenant)?;
+                if let Some(client_secret) = client_secret {
+                    Ok(Credential::ClientSecret {
+                        client_id,
+                        tenant,
+                        client_secret,
+                    })
+                } else if let Some(certificate_path) = certificate_path {
+                    Ok(Credential::ClientCertificate {
+                        client_id,
+                        tenant,
+                        certificate_path,
+                    })
+                } else {
+                    Err(CredentialError::MissingSecret)
+                }
+            }
+            None => Ok(Credential::DeviceCode { tenant }),
+        }
+    }
+
+    pub fn is_interactive(&self) -> bool {
+        matches!(self, Credential::DeviceCode { .. })
+    }
+
+    pub fn login_arguments(&self) -> Vec<String> {
+        let mut args = vec!["login".to_string()];
+        match self {
+            Credential::ClientSecret {
+                client_id,
+                tenant,
+                client_secret,
+            } => {
+                args.extend(service_principal_arguments(client_id, tenant));
+                args.push("--password".to_string());
+                args.push(client_secret.to_string());
+            }
+            Credential::ClientCertificate {
+                client_id,
+                tenant,
+                certificate_path,
+            } => {
+                args.extend(service_principal_arguments(client_id, tenant));
+                args.push("--password".to_string());
+                args.push(certificate_path.to_string());
+            }
+            Credential::ManagedIdentity { client_id, .. } => {
+                args.push("--identity".to_string());
+                if let Some(id) = client_id {
+                    args.push("--username".to_string());
+                    args.push(id.to_string());
+                }
+            }
+            Credential::DeviceCode { tenant } => {
+                args.push("--use-device-code".to_string());
+                if let Some(t) = tenant {
+                    args.push("--tenant".to_string());
+                    args.push(t.to_string());
+                }
+            }
+        }
+        args
+    }
+}
+
+fn service_principal_arguments(client_id: &str, tenant: &str) -> Vec<String> {
+    vec![
+        "--service-principal".to_string(),
+        "--username".to_string(),
+        client_id.to_string(),
+        "--tenant".to_string(),
+        tenant.to_string(),
+    ]
+}
+
+impl Default for Credential {
+    fn default() -> Self {
+        Credential::DeviceCode { tenant: None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn some(value: &str) -> Option<String> {
+        Some(value.to_string())
+    }
+
+    #[test]
+    fn defaults_to_device_code() {
+        let credential = Credential::from_options(None, some("tenant"), None, None, false);
+
+        assert_eq!(
+            credential.unwrap(),
+            Credential::DeviceCode {
+                tenant: some("tenant")
+            }
+        );
+    }
+
+    #[test]
+    fn client_secret_selects_service_principal() {
+        let credential =
+            Credential::from_options(some("app"), some("tenant"), some("secret"), None, false)
+                .unwrap();
+
+        assert_eq!(
+            credential.login_arguments(),
+            vec![
+                "login",
+                "--service-principal",
+                "--username",
+                "app",
+                "--tenant",
+                "tenant",
+                "--password",
+                "secret"
+            ]
+        );
+        assert!(!credential.is_interactive());
+    }
+
+    #[test]
+    fn certificate_selects_service_principal() {
+        let credential = Credential::from_options(
+            some("app"),
+            some("tenant"),
+            None,
+            some("/certs/app.pem"),
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(
+            credential,
+            Credential::ClientCertificate {
+                client_id: "app".to_string(),
+                tenant: "tenant".to_string(),
+                certificate_path: "/certs/app.pem".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn service_principal_requires_tenant_and_secret() {
+        assert!(Credential::from_options(some("app"), None, some("secret"), None, false).is_err());
+        assert!(Credential::from_options(some("app"), some("tenant"), None, None, false).is_err());
+    }
+
+    #[test]
+    fn options_are_only_checked_when_logging_in() {
+        let options = CredentialOptions {
+            client_id: some("app"),
+            client_secret: some("secret"),
+            ..Default::default()
+        };
+
+        assert!(options.credential().is_err());
+        assert!(!serde_json::to_string(&options).unwrap().contains("secret"));
+    }
+
+    #[test]
+    fn managed_identity_takes_precedence() {
+        let credential =
+            Credential::from_options(some("identity"), None, some("secret"), None, true).unwrap();
+
+        assert_eq!(
+            credential.login_arguments(),
+            vec!["login", "--identity", "--username", "identity"]
+        );
+    }
+}
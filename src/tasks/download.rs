@@ -1,36 +1,72 @@
 use reqwest::{Client, Url};
+use std::fs;
 use std::fs::File;
+use std::io;
 use std::path::Path;
 
-#[allow(clippy::match_wild_err_arm)]
 pub fn download_file(uri: &str, filename: &str) {
     if Path::new(filename).exists() {
         println!("\t\tFile {} already exists.  Skipping.", filename);
     } else {
-        let client = Client::new();
         println!("\t\tDownloading {} from {}", filename, uri);
-        match client.get(uri).send() {
-            Ok(mut res) => {
-                let mut file = File::create(filename).unwrap();
-                res.copy_to(&mut file).unwrap();
-            }
-            Err(_) => panic!("Boom!"),
+        if let Err(e) = save_file(uri, Path::new(filename)) {
+            panic!("Failed to download {}: {}", uri, e);
         }
     }
 }
 
+// Saves a remote file, or copies a local one (like the files of a bundle).
+pub fn save_file(uri: &str, target: &Path) -> Result<(), io::Error> {
+    if Url::parse(uri).is_err() {
+        return fs::copy(uri, target).map(|_| ());
+    }
+
+    let mut res = Client::new().get(uri).send().map_err(io::Error::other)?;
+    if !res.status().is_success() {
+        return Err(io::Error::other(format!(
+            "the server responded with {}",
+            res.status()
+        )));
+    }
+    let mut file = File::create(target)?;
+    res.copy_to(&mut file).map_err(io::Error::other)?;
+    Ok(())
+}
+
+// Local sources are relative to the directory `demo up` runs in, which is not the directory the
+// files are downloaded in.  Anything else (like `git@github.com:org/repo`) is left as it is.
+pub fn resolve_source(uri: &str, directory: &Path) -> String {
+    let path = directory.join(uri);
+    if Url::parse(uri).is_err() && path.exists() {
+        path.to_string_lossy().to_string()
+    } else {
+        uri.to_string()
+    }
+}
+
+// The size of a download, when the server reports it.
+pub fn get_content_length(uri: &str) -> Option<u64> {
+    let client = Client::new();
+    match client.head(uri).send() {
+        Ok(res) if res.status().is_success() => res.content_length(),
+        _ => None,
+    }
+}
+
 pub fn get_filename(uri: &str, filename: &str) -> String {
-    let url = Url::parse(uri).unwrap();
-
-    let mut target_filename = filename;
-    if target_filename.is_empty() {
-        if let Some(segments) = url.path_segments() {
-            for segment in segments {
-                target_filename = segment;
-            }
-        };
-    };
-    target_filename.to_string()
+    if !filename.is_empty() {
+        return filename.to_string();
+    }
+    match Url::parse(uri) {
+        Ok(url) => match url.path_segments() {
+            Some(mut segments) => segments.next_back().unwrap_or_default().to_string(),
+            None => "".to_string(),
+        },
+        Err(_) => Path::new(uri)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default(),
+    }
 }
 
 #[cfg(test)]
@@ -52,4 +88,30 @@ mod tests {
 
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn get_filename_of_a_local_file() {
+        assert_eq!(
+            get_filename("files/SRE10/slides/SRE10.pptx", ""),
+            "SRE10.pptx".to_string()
+        );
+    }
+
+    #[test]
+    fn local_sources_are_relative_to_the_directory() {
+        let directory = Path::new("./test");
+
+        assert_eq!(
+            resolve_source("artifacts/demo_template.json", directory),
+            "./test/artifacts/demo_template.json"
+        );
+        assert_eq!(
+            resolve_source("git@github.com:microsoft/demo-up", directory),
+            "git@github.com:microsoft/demo-up"
+        );
+        assert_eq!(
+            resolve_source("https://somewhere.com/somefile.txt", directory),
+            "https://somewhere.com/somefile.txt"
+        );
+    }
 }
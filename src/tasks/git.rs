@@ -1,15 +1,118 @@
-use git2::Repository;
-use std::path::PathBuf;
+use git2::build::RepoBuilder;
+use git2::{Repository, StatusOptions};
+use std::path::{Path, PathBuf};
 
-pub fn git_clone(url: &str, repo_path: PathBuf) {
+pub fn git_clone(url: &str, branch: Option<&str>, repo_path: PathBuf) {
     let _repo = match Repository::open(&repo_path) {
         Ok(repo) => {
             println!("\t\tRepository already exists.  Skipping.");
             repo
         }
-        Err(_) => match Repository::clone(url, repo_path) {
+        Err(_) => match clone(url, branch, &repo_path) {
             Ok(repo) => repo,
             Err(e) => panic!("Failed to clone {}", e),
         },
     };
 }
+
+fn clone(url: &str, branch: Option<&str>, repo_path: &Path) -> Result<Repository, git2::Error> {
+    let mut builder = RepoBuilder::new();
+    if let Some(b) = branch {
+        builder.branch(b);
+    }
+    builder.clone(url, repo_path)
+}
+
+// A bare copy of every branch and tag, which can be cloned from like the original repository.
+pub fn git_mirror(url: &str, repo_path: &Path) -> Result<(), git2::Error> {
+    let repo = RepoBuilder::new().bare(true).clone(url, repo_path)?;
+    let mut remote = repo.find_remote("origin")?;
+    remote.fetch(
+        &["+refs/heads/*:refs/heads/*", "+refs/tags/*:refs/tags/*"],
+        None,
+        None,
+    )
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RepoState {
+    pub branch: Option<String>,
+    pub dirty: bool,
+}
+
+// The checked out branch of a local repository and whether it has uncommitted changes.
+pub fn git_status(repo_path: &Path) -> Option<RepoState> {
+    let repo = Repository::open(repo_path).ok()?;
+    let branch = match repo.head() {
+        Ok(head) => head.shorthand().map(|b| b.to_string()),
+        Err(_) => None,
+    };
+    let mut options = StatusOptions::new();
+    options.include_untracked(true);
+    let dirty = match repo.statuses(Some(&mut options)) {
+        Ok(statuses) => !statuses.is_empty(),
+        Err(_) => false,
+    };
+    Some(RepoState { branch, dirty })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn git_status_reports_uncommitted_changes() {
+        let path = env::temp_dir().join("demo-up-git-status");
+        let _ = fs::remove_dir_all(&path);
+        Repository::init(&path).unwrap();
+
+        let clean = git_status(&path).unwrap();
+        fs::write(path.join("README.md"), "demo").unwrap();
+        let dirty = git_status(&path).unwrap();
+        fs::remove_dir_all(&path).unwrap();
+
+        assert!(!clean.dirty);
+        assert!(dirty.dirty);
+    }
+
+    #[test]
+    fn mirrors_have_every_branch() {
+        let source = env::temp_dir().join("demo-up-git-mirror-source");
+        let mirror = env::temp_dir().join("demo-up-git-mirror");
+        let _ = fs::remove_dir_all(&source);
+        let _ = fs::remove_dir_all(&mirror);
+        let repo = Repository::init(&source).unwrap();
+        let signature = git2::Signature::now("demo", "demo@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap());
+        let commit = repo
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                "Initial commit",
+                &tree.unwrap(),
+                &[],
+            )
+            .unwrap();
+        repo.branch("tour", &repo.find_commit(commit).unwrap(), false)
+            .unwrap();
+
+        git_mirror(&source.to_string_lossy(), &mirror).unwrap();
+        let mirrored = Repository::open_bare(&mirror).unwrap();
+        let has_tour = mirrored.find_reference("refs/heads/tour").is_ok();
+        fs::remove_dir_all(&source).unwrap();
+        fs::remove_dir_all(&mirror).unwrap();
+
+        assert!(has_tour);
+    }
+
+    #[test]
+    fn git_status_is_none_outside_a_repository() {
+        assert_eq!(
+            git_status(&env::temp_dir().join("demo-up-not-a-repo")),
+            None
+        );
+    }
+}
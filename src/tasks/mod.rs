@@ -1,7 +1,21 @@
+pub mod arm_rest;
 pub mod az_cli;
+pub mod backend;
+pub mod cassette;
+pub mod credential;
 pub mod download;
 pub mod git;
+pub mod prompt;
 
-pub use self::az_cli::{deploy_template, run_cli_command, set_azure_environment};
-pub use self::download::{download_file, get_filename};
-pub use self::git::git_clone;
+pub use self::az_cli::{
+    cli_command_line, preview_template, run_cli_command, set_azure_environment,
+    use_isolated_azure_config,
+};
+pub use self::backend::{get_backend, CloudBackend, AZ_CLI_BACKEND};
+pub use self::cassette::use_replay_cassette;
+pub use self::credential::CredentialOptions;
+pub use self::download::{
+    download_file, get_content_length, get_filename, resolve_source, save_file,
+};
+pub use self::git::{git_clone, git_mirror};
+pub use self::prompt::confirm;
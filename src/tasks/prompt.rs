@@ -0,0 +1,36 @@
+use std::io;
+use std::io::prelude::*;
+
+pub fn confirm(message: &str) -> bool {
+    print!("{} [y/N] ", message);
+    io::stdout().flush().unwrap();
+
+    let mut answer = String::new();
+    match io::stdin().read_line(&mut answer) {
+        Ok(_) => is_affirmative(&answer),
+        Err(_) => false,
+    }
+}
+
+fn is_affirmative(answer: &str) -> bool {
+    let answer = answer.trim().to_lowercase();
+    answer == "y" || answer == "yes"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yes_is_affirmative() {
+        assert!(is_affirmative("y\n"));
+        assert!(is_affirmative("Yes\n"));
+    }
+
+    #[test]
+    fn anything_else_is_not_affirmative() {
+        assert!(!is_affirmative("\n"));
+        assert!(!is_affirmative("no\n"));
+        assert!(!is_affirmative("yep\n"));
+    }
+}
@@ -1,24 +1,49 @@
-use crate::config::get_config;
+use crate::config::naming::{check_resource_group_names, NamingError};
+use crate::config::regions::{check_locations, LocationError};
+use crate::config::{get_verified_config, Command, ConfigError, TourConfig, Verification};
 use serde::{Deserialize, Serialize};
 
+use crate::tasks::backend::{CloudBackendError, ACCESS_TOKEN_VARIABLE};
 use crate::tasks::*;
 use custom_error::custom_error;
+use std::collections::BTreeMap;
 use std::env;
 use std::path::PathBuf;
 
+// How long the resource groups for an event are kept before they are considered expired.
+pub const DEFAULT_EXPIRES_IN_HOURS: u32 = 72;
+
 custom_error! {
     pub DemoUpError
     Failed = "Failed to create the environment",
+    ConfigFailure{source: ConfigError} = "{source}",
+    BackendFailure{source: CloudBackendError} = "{source}",
+    LocationFailure{source: LocationError} = "{source}",
+    NamingFailure{source: NamingError} = "{source}",
+    MissingSubscription{session: String} = "A subscription needs to be defined for {session}, either in the configuration file or on the command line.",
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct UpCommand {
     pub config_path: String,
+    pub verification: Verification,
     pub subscription: String,
+    pub credential: CredentialOptions,
     pub event: Option<String>,
     pub session_names: Option<Vec<String>>,
     pub location: Option<String>,
+    pub allow_unknown_location: bool,
     pub exclude: Option<Vec<String>>,
+    pub profile: Option<String>,
+    pub what_if: bool,
+    pub confirm: bool,
+    pub backend: String,
+    pub azure_profile: Option<String>,
+    pub shared_azure_config: bool,
+    pub presenter: Option<String>,
+    pub expires_in: u32,
+    pub directory: Option<PathBuf>,
+    pub cassette: Option<PathBuf>,
 }
 
 impl UpCommand {
@@ -29,6 +54,13 @@ impl UpCommand {
         }
     }
 
+    pub fn azure_profile(&self) -> String {
+        match self.azure_profile {
+            Some(ref profile) => profile.to_string(),
+            None => self.event(),
+        }
+    }
+
     pub fn session_names(&self) -> Vec<String> {
         match self.session_names.clone() {
             Some(s) => s,
@@ -40,20 +72,46 @@ impl UpCommand {
         let cli_args = self.clone();
 
         println!("Loading the configuration from {}\n", &cli_args.config_path);
-        let mut tour_config = get_config(&cli_args.config_path);
+        let mut tour_config = get_verified_config(
+            &cli_args.config_path,
+            cli_args.profile.as_deref(),
+            &cli_args.verification,
+        )?;
         tour_config.update(&cli_args);
 
+        if let Some(ref cassette) = self.cassette {
+            use_replay_cassette(cassette);
+        }
+
+        let mut context: Option<SubscriptionContext> = None;
+        let mut subscriptions_used: BTreeMap<String, Vec<String>> = BTreeMap::new();
         if !self.skip_section("Commands") {
-            set_azure_environment(&tour_config.subscription()).unwrap();
+            for location in check_locations(&tour_config, self.allow_unknown_location)? {
+                println!(
+                    "Warning: {} is not a known Azure region, Azure will check it when it is used.",
+                    location
+                );
+            }
+            check_resource_group_names(&tour_config)?;
+            check_subscriptions(&tour_config)?;
+            if !self.shared_azure_config {
+                let directory = use_isolated_azure_config(&self.azure_profile())
+                    .map_err(CloudBackendError::from)?;
+                println!("Using the Azure CLI profile in {}\n", directory.display());
+            }
         }
 
         let starting_directory = env::current_dir().unwrap();
+        let sessions_directory = match self.directory {
+            Some(ref d) => d.to_path_buf(),
+            None => starting_directory.to_path_buf(),
+        };
 
         for s in tour_config.sessions() {
             let session_name = s.name();
             println!("\t{}: Starting setup", &session_name);
 
-            let session_directory = starting_directory.to_path_buf().join(s.name.unwrap());
+            let session_directory = sessions_directory.join(s.name.unwrap());
             let slides_directory = session_directory.join("slides");
             let video_directory = session_directory.join("videos");
             let source_directory = session_directory.join("src");
@@ -61,56 +119,62 @@ impl UpCommand {
             println!("\t{}: Creating session directory", &session_name);
             create_directory(&session_directory);
 
-            if !self.skip_section("Slides") && s.slides.is_some() {
-                println!("\t{}: Creating slides directory.", &session_name);
-                create_directory(&slides_directory);
-
-                let slides = s.slides.unwrap();
-                env::set_current_dir(&slides_directory).unwrap();
-                let slides_url = slides.url.unwrap();
-                let default_filename = match slides.file_name {
-                    Some(n) => n,
-                    None => "".to_string(),
-                };
-                let slides_filename = get_filename(&slides_url, &default_filename);
-                println!(
-                    "\t{}: Downloading {} to the slides directory.",
-                    &session_name, &slides_filename
-                );
-
-                download_file(&slides_url, &slides_filename);
-            };
-
-            if !self.skip_section("Videos") && s.videos.is_some() {
-                println!("\t{}: Creating video directory.", &session_name);
-                create_directory(&video_directory);
-
-                env::set_current_dir(&video_directory).unwrap();
-                for video in s.videos.unwrap() {
-                    let video_url = video.url.unwrap();
-                    let video_filename = get_filename(&video_url, "");
+            if !self.skip_section("Slides") {
+                if let Some(slides) = s.slides {
+                    println!("\t{}: Creating slides directory.", &session_name);
+                    create_directory(&slides_directory);
+
+                    env::set_current_dir(&slides_directory).unwrap();
+                    let slides_url = resolve_source(&slides.url.unwrap(), &starting_directory);
+                    let default_filename = match slides.file_name {
+                        Some(n) => n,
+                        None => "".to_string(),
+                    };
+                    let slides_filename = get_filename(&slides_url, &default_filename);
                     println!(
-                        "\t{}: Downloading {} to the video directory.",
-                        &session_name, &video_filename
+                        "\t{}: Downloading {} to the slides directory.",
+                        &session_name, &slides_filename
                     );
 
-                    download_file(&video_url, &video_filename);
+                    download_file(&slides_url, &slides_filename);
                 }
             };
 
-            if !self.skip_section("GitRepos") && s.git_repos.is_some() {
-                println!("\t{}: Creating source directory.", &session_name);
-                create_directory(&source_directory);
-                for repo_url in s.git_repos.unwrap() {
-                    let repo_name = get_filename(&repo_url, "");
-                    let repo_dir = &source_directory.join(&repo_name);
-                    println!(
-                        "\t{}: Cloning {} into {}",
-                        &session_name,
-                        &repo_name,
-                        &repo_dir.to_str().unwrap()
-                    );
-                    git_clone(&repo_url, repo_dir.to_path_buf());
+            if !self.skip_section("Videos") {
+                if let Some(videos) = s.videos {
+                    println!("\t{}: Creating video directory.", &session_name);
+                    create_directory(&video_directory);
+
+                    env::set_current_dir(&video_directory).unwrap();
+                    for video in videos {
+                        let video_url = resolve_source(&video.url.unwrap(), &starting_directory);
+                        let video_filename = get_filename(&video_url, "");
+                        println!(
+                            "\t{}: Downloading {} to the video directory.",
+                            &session_name, &video_filename
+                        );
+
+                        download_file(&video_url, &video_filename);
+                    }
+                }
+            };
+
+            if !self.skip_section("GitRepos") {
+                if let Some(git_repos) = s.git_repos {
+                    println!("\t{}: Creating source directory.", &session_name);
+                    create_directory(&source_directory);
+                    for repo in git_repos {
+                        let repo_url = resolve_source(&repo.url(), &starting_directory);
+                        let repo_name = get_filename(&repo_url, "");
+                        let repo_dir = &source_directory.join(&repo_name);
+                        println!(
+                            "\t{}: Cloning {} into {}",
+                            &session_name,
+                            &repo_name,
+                            &repo_dir.to_str().unwrap()
+                        );
+                        git_clone(&repo_url, repo.branch.as_deref(), repo_dir.to_path_buf());
+                    }
                 }
             };
 
@@ -122,30 +186,157 @@ impl UpCommand {
 
                     commands.sort_by_key(|c| c.order());
                     for command in commands {
-                        if command.template.is_some() {
-                            println!("\t\t{}: Deploying an ARM template", &session_name);
+                        let subscription = command.subscription.clone().unwrap_or_default();
+                        let switch = match context {
+                            Some(ref c) => c.subscription != subscription,
+                            None => true,
+                        };
+                        if switch {
+                            context = Some(self.use_subscription(&subscription)?);
+                        }
+                        let current = context.as_mut().unwrap();
+                        let used = subscriptions_used
+                            .entry(session_name.to_string())
+                            .or_default();
+                        if !used.contains(&current.name) {
+                            used.push(current.name.to_string());
+                        }
 
-                            deploy_template(&command).unwrap();
+                        if command.template.is_some() {
+                            if self.preview(&command) {
+                                println!(
+                                    "\t\t{}: Previewing the ARM template deployment",
+                                    &session_name
+                                );
+
+                                self.use_azure_cli(current)?;
+                                let preview =
+                                    preview_template(&command).map_err(CloudBackendError::from)?;
+                                if let Some(output) = preview {
+                                    println!("{}", String::from_utf8_lossy(&output.stdout));
+                                }
+                            }
+
+                            if self.should_deploy(&command) {
+                                println!("\t\t{}: Deploying an ARM template", &session_name);
+
+                                current.backend.deploy_template(&command)?;
+                            } else {
+                                println!("\t\t{}: Skipping the deployment", &session_name);
+                            }
                         };
                         if command.cli.is_some() {
-                            println!("\t\t{}: Running a CLI command", &session_name);
-
-                            run_cli_command(&command).unwrap();
+                            if self.what_if {
+                                println!(
+                                    "\t\t{}: Would run: {}",
+                                    &session_name,
+                                    cli_command_line(&command)
+                                );
+                            }
+
+                            if self.should_run() {
+                                println!("\t\t{}: Running a CLI command", &session_name);
+
+                                self.use_azure_cli(current)?;
+                                run_cli_command(&command).unwrap();
+                            } else {
+                                println!("\t\t{}: Skipping the CLI command", &session_name);
+                            }
                         };
                     }
                 };
             };
         }
 
+        if !subscriptions_used.is_empty() {
+            println!("\nSubscriptions used:");
+            for (session_name, names) in &subscriptions_used {
+                println!("\t{}: {}", session_name, names.join(", "));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Points the Azure CLI (when it is used) and the backend at the subscription.
+    fn use_subscription(&self, subscription: &str) -> Result<SubscriptionContext, DemoUpError> {
+        // The REST backend only needs the Azure CLI when no access token is supplied.
+        let azure_cli = self.backend == AZ_CLI_BACKEND || env::var(ACCESS_TOKEN_VARIABLE).is_err();
+        if azure_cli {
+            set_azure_environment(subscription, &self.credential)
+                .map_err(CloudBackendError::from)?;
+        }
+        let backend = get_backend(&self.backend, subscription)?;
+        let name = match backend.show_account()?.subscription_name {
+            Some(n) => n,
+            None => subscription.to_string(),
+        };
+        println!("Provisioning into the {} subscription.\n", name);
+
+        Ok(SubscriptionContext {
+            subscription: subscription.to_string(),
+            name,
+            backend,
+            azure_cli,
+        })
+    }
+
+    // What-if previews and `cli` commands always run through the Azure CLI, so it is pointed at
+    // the subscription before the first of them even when the backend does not use it.
+    fn use_azure_cli(&self, context: &mut SubscriptionContext) -> Result<(), DemoUpError> {
+        if !context.azure_cli {
+            set_azure_environment(&context.subscription, &self.credential)
+                .map_err(CloudBackendError::from)?;
+            context.azure_cli = true;
+        }
         Ok(())
     }
 
+    fn preview(&self, command: &Command) -> bool {
+        self.what_if || command.what_if()
+    }
+
+    // A global --what-if only previews unless the user confirms the changes, while a per-command
+    // what_if shows the preview and then deploys.
+    fn should_deploy(&self, command: &Command) -> bool {
+        if !self.preview(command) {
+            true
+        } else if self.confirm {
+            confirm("\t\tDeploy these changes?")
+        } else {
+            !self.what_if
+        }
+    }
+
+    // Azure CLI commands cannot be previewed, so a global --what-if only shows them unless the
+    // user confirms running them.
+    fn should_run(&self) -> bool {
+        !self.what_if || (self.confirm && confirm("\t\tRun this command?"))
+    }
+
     fn skip_section(&self, section: &str) -> bool {
-        match self.exclude {
-            Some(ref exclude) if exclude.contains(&section.to_string()) => true,
-            _ => false,
+        matches!(self.exclude, Some(ref exclude) if exclude.contains(&section.to_string()))
+    }
+}
+
+// The subscription the Azure CLI and the backend currently target.
+struct SubscriptionContext {
+    subscription: String,
+    name: String,
+    backend: Box<dyn CloudBackend>,
+    // Whether the Azure CLI targets the subscription too.
+    azure_cli: bool,
+}
+
+fn check_subscriptions(tour_config: &TourConfig) -> Result<(), DemoUpError> {
+    for session in tour_config.sessions() {
+        if session.commands().iter().any(|c| c.subscription.is_none()) {
+            return Err(DemoUpError::MissingSubscription {
+                session: session.name(),
+            });
         }
     }
+    Ok(())
 }
 
 fn create_directory(path: &PathBuf) {
@@ -158,11 +349,24 @@ impl Default for UpCommand {
     fn default() -> UpCommand {
         UpCommand {
             config_path: "".to_string(),
+            verification: Verification::default(),
             subscription: "".to_string(),
+            credential: CredentialOptions::default(),
             event: None,
             session_names: None,
             location: None,
+            allow_unknown_location: false,
             exclude: None,
+            profile: None,
+            what_if: false,
+            confirm: false,
+            backend: AZ_CLI_BACKEND.to_string(),
+            azure_profile: None,
+            shared_azure_config: false,
+            presenter: None,
+            expires_in: DEFAULT_EXPIRES_IN_HOURS,
+            directory: None,
+            cassette: None,
         }
     }
 }
@@ -170,20 +374,171 @@ impl Default for UpCommand {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tasks::cassette::{record, Interaction};
+    use std::path::Path;
 
     #[test]
     fn skips_correct_section() {
-        let mut config = UpCommand::default();
-        config.exclude = Some(vec!["Slides".to_string()]);
+        let config = UpCommand {
+            exclude: Some(vec!["Slides".to_string()]),
+            ..Default::default()
+        };
 
         assert!(config.skip_section("Slides"));
     }
 
     #[test]
     fn does_not_skip_correct_section() {
-        let mut config = UpCommand::default();
-        config.exclude = Some(vec!["Video".to_string()]);
+        let config = UpCommand {
+            exclude: Some(vec!["Video".to_string()]),
+            ..Default::default()
+        };
 
         assert!(!config.skip_section("Slides"));
     }
+
+    #[test]
+    fn every_command_needs_a_subscription() {
+        let session = crate::config::Session {
+            name: Some("SRE10".to_string()),
+            commands: Some(vec![Command::default()]),
+            ..Default::default()
+        };
+        let tour_config = TourConfig {
+            sessions: Some(vec![session]),
+            ..Default::default()
+        };
+
+        match check_subscriptions(&tour_config) {
+            Err(DemoUpError::MissingSubscription { session }) => assert_eq!(session, "SRE10"),
+            _ => panic!("Expected a missing subscription."),
+        }
+    }
+
+    #[test]
+    fn execute_replays_recorded_azure_cli_session() {
+        let directory = tempfile::tempdir().unwrap();
+
+        let config = UpCommand {
+            directory: Some(directory.path().to_path_buf()),
+            cassette: Some(PathBuf::from("./test/artifacts/up_cassette.json")),
+            shared_azure_config: true,
+            config_path: "./test/artifacts/single_session_config.yml".to_string(),
+            event: Some("tour".to_string()),
+            session_names: Some(vec!["SRE30".to_string()]),
+            location: Some("westus2".to_string()),
+            exclude: Some(vec![
+                "Slides".to_string(),
+                "Videos".to_string(),
+                "GitRepos".to_string(),
+            ]),
+            ..Default::default()
+        };
+
+        let result = config.execute();
+
+        assert!(result.is_ok());
+        assert!(directory.path().join("SRE30").is_dir());
+        assert!(!Path::new("SRE30").exists());
+    }
+
+    #[test]
+    fn unknown_subscriptions_are_errors() {
+        let directory = tempfile::tempdir().unwrap();
+        let cassette = directory.path().join("cassette.json");
+        let account = Interaction {
+            args: vec!["account", "show", "--output", "json"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            exit_code: 0,
+            stdout: r#"{"id": "00000000-0000-0000-0000-000000000000", "name": "Ignite the Tour", "tenantId": "11111111-1111-1111-1111-111111111111"}"#.to_string(),
+            stderr: String::new(),
+        };
+        let set = Interaction {
+            args: vec!["account", "set", "--subscription", "Ignite the Tuor"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            exit_code: 1,
+            stdout: String::new(),
+            stderr:
+                "ERROR: The subscription of 'Ignite the Tuor' doesn't exist in cloud 'AzureCloud'."
+                    .to_string(),
+        };
+        record(&cassette, account).unwrap();
+        record(&cassette, set).unwrap();
+
+        let config = UpCommand {
+            directory: Some(directory.path().to_path_buf()),
+            cassette: Some(cassette),
+            shared_azure_config: true,
+            config_path: "./test/artifacts/single_session_config.yml".to_string(),
+            subscription: "Ignite the Tuor".to_string(),
+            event: Some("tour".to_string()),
+            session_names: Some(vec!["SRE30".to_string()]),
+            location: Some("westus2".to_string()),
+            exclude: Some(vec![
+                "Slides".to_string(),
+                "Videos".to_string(),
+                "GitRepos".to_string(),
+            ]),
+            ..Default::default()
+        };
+
+        match config.execute() {
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "The Azure CLI failed: Unable to find the Ignite the Tuor subscription."
+            ),
+            Ok(_) => panic!("Expected the subscription to be missing."),
+        }
+    }
+
+    #[test]
+    fn azure_profile_defaults_to_event() {
+        let mut config = UpCommand {
+            event: Some("tour".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(config.azure_profile(), "tour");
+
+        config.azure_profile = Some("rehearsal".to_string());
+        assert_eq!(config.azure_profile(), "rehearsal");
+    }
+
+    #[test]
+    fn deploys_without_what_if() {
+        let config = UpCommand::default();
+        let command = Command::default();
+
+        assert!(!config.preview(&command));
+        assert!(config.should_deploy(&command));
+        assert!(config.should_run());
+    }
+
+    #[test]
+    fn global_what_if_only_previews() {
+        let config = UpCommand {
+            what_if: true,
+            ..Default::default()
+        };
+        let command = Command::default();
+
+        assert!(config.preview(&command));
+        assert!(!config.should_deploy(&command));
+        assert!(!config.should_run());
+    }
+
+    #[test]
+    fn command_what_if_previews_and_deploys() {
+        let config = UpCommand::default();
+        let command = Command {
+            what_if: Some(true),
+            ..Default::default()
+        };
+
+        assert!(config.preview(&command));
+        assert!(config.should_deploy(&command));
+    }
 }
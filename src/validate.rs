@@ -0,0 +1,40 @@
+use crate::config::validation::validate;
+use crate::config::{get_config, ConfigError};
+use custom_error::custom_error;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+custom_error! {
+    pub DemoValidateError
+    ConfigFailure{source: ConfigError} = "{source}",
+    Invalid{count: usize} = "Found {count} problem(s) in the configuration file.",
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ValidateCommand {
+    pub config_path: String,
+}
+
+impl ValidateCommand {
+    pub fn new(config_path: String) -> Self {
+        ValidateCommand { config_path }
+    }
+
+    pub fn execute(&self) -> Result<(), DemoValidateError> {
+        let tour_config = get_config(&self.config_path)?;
+
+        let problems = validate(&tour_config, Path::new(""));
+        for problem in &problems {
+            println!("{}: {}", self.config_path, problem);
+        }
+
+        if problems.is_empty() {
+            println!("{} is valid.", self.config_path);
+            Ok(())
+        } else {
+            Err(DemoValidateError::Invalid {
+                count: problems.len(),
+            })
+        }
+    }
+}